@@ -0,0 +1,639 @@
+//! Compares two versions of a [`Difficulty`] or an [info file](Beatmap), similar to a code review diff.
+//!
+//! Objects are compared using their serialized fields, and are matched in three passes:
+//! 1. Identical objects are unchanged.
+//! 2. Objects of the same type at the same beat and position (and with the same event type or group ID) are [changed](ObjectChange::Changed).
+//! 3. Objects that are identical except for their beat and position are [moved](ObjectChange::Moved),
+//!    as long as they moved less than [`MOVE_WINDOW`] beats.
+//!
+//! Any other objects are [added](ObjectChange::Added) or [removed](ObjectChange::Removed).
+//!
+//! Diffs can be displayed as a human-readable summary, or serialized into JSON.
+
+use crate::difficulty::lightshow::FxEventContainer;
+use crate::difficulty::{Difficulty, ObjectRef};
+use crate::info::{Beatmap, DifficultyInfo};
+use crate::timing_traits::Timed;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+
+/// The maximum number of beats an object can move and still be considered [moved](ObjectChange::Moved).
+pub const MOVE_WINDOW: f32 = 1.0;
+/// The serialized fields that decide an object's position, including the tail of arcs and chains.
+const POSITION_FIELDS: [&str; 6] = ["b", "x", "y", "tb", "tx", "ty"];
+/// The serialized fields that must match for an object to be [changed](ObjectChange::Changed) rather than replaced.
+const IDENTITY_FIELDS: [&str; 5] = ["b", "x", "y", "et", "g"];
+
+/// The differences between two versions of a difficulty, returned by [`Difficulty::diff`].
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct DifficultyDiff {
+    /// Changes to the difficulty's own fields, such as its version.
+    pub fields: Vec<FieldChange>,
+    /// Changes to the difficulty's objects, grouped by type and sorted by beat.
+    pub objects: Vec<ObjectChange>,
+}
+
+impl DifficultyDiff {
+    /// Returns true if there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.objects.is_empty()
+    }
+}
+
+/// A change to a single object, found by [`Difficulty::diff`].
+///
+/// The old object refers to the original difficulty, and the new object refers to the updated difficulty.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub enum ObjectChange {
+    Added {
+        new: ObjectRef,
+        beat: f32,
+    },
+    Removed {
+        old: ObjectRef,
+        beat: f32,
+    },
+    /// The object moved to a different beat or position, without any other changes.
+    Moved {
+        old: ObjectRef,
+        new: ObjectRef,
+        old_beat: f32,
+        new_beat: f32,
+    },
+    /// The object is at the same beat and position, but its other fields changed.
+    Changed {
+        old: ObjectRef,
+        new: ObjectRef,
+        beat: f32,
+        fields: Vec<FieldChange>,
+    },
+}
+
+impl ObjectChange {
+    /// Returns the beat of the change, using the new beat for moved objects.
+    pub fn get_beat(&self) -> f32 {
+        match self {
+            ObjectChange::Added { beat, .. }
+            | ObjectChange::Removed { beat, .. }
+            | ObjectChange::Changed { beat, .. } => *beat,
+            ObjectChange::Moved { new_beat, .. } => *new_beat,
+        }
+    }
+}
+
+/// A change to a single serialized field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    /// The path to the field, using its serialized names separated by slashes (i.e. `_difficultyBeatmapSets/0/_beatmapCharacteristicName`).
+    ///
+    /// Fields that only exist in one version will have a null value in the other.
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// The differences between two versions of an info file or difficulty info,
+/// returned by [`Beatmap::diff`] and [`DifficultyInfo::diff`].
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct FieldDiff {
+    pub fields: Vec<FieldChange>,
+}
+
+impl Difficulty {
+    /// Compares the difficulty to an updated version of itself.
+    ///
+    /// For details, see the [module docs](crate::diff).
+    pub fn diff(&self, new: &Difficulty) -> DifficultyDiff {
+        let mut objects = Vec::new();
+
+        diff_list(
+            &self.bpm_events,
+            &new.bpm_events,
+            ObjectRef::BpmEvent,
+            &mut objects,
+        );
+        diff_list(
+            &self.lane_rotation_events,
+            &new.lane_rotation_events,
+            ObjectRef::LaneRotationEvent,
+            &mut objects,
+        );
+        diff_list(&self.notes, &new.notes, ObjectRef::Note, &mut objects);
+        diff_list(&self.bombs, &new.bombs, ObjectRef::Bomb, &mut objects);
+        diff_list(&self.walls, &new.walls, ObjectRef::Wall, &mut objects);
+        diff_list(&self.arcs, &new.arcs, ObjectRef::Arc, &mut objects);
+        diff_list(&self.chains, &new.chains, ObjectRef::Chain, &mut objects);
+        diff_list(
+            &self.waypoints,
+            &new.waypoints,
+            ObjectRef::Waypoint,
+            &mut objects,
+        );
+        diff_list(
+            &self.basic_events,
+            &new.basic_events,
+            ObjectRef::BasicEvent,
+            &mut objects,
+        );
+        diff_list(
+            &self.color_boost_events,
+            &new.color_boost_events,
+            ObjectRef::ColorBoostEvent,
+            &mut objects,
+        );
+        diff_list(
+            &self.color_event_boxes,
+            &new.color_event_boxes,
+            ObjectRef::ColorEventBox,
+            &mut objects,
+        );
+        diff_list(
+            &self.rotation_event_boxes,
+            &new.rotation_event_boxes,
+            ObjectRef::RotationEventBox,
+            &mut objects,
+        );
+        diff_entries(
+            get_fx_entries(&self.fx_event_boxes),
+            get_fx_entries(&new.fx_event_boxes),
+            ObjectRef::FxEventBox,
+            &mut objects,
+        );
+        diff_list(
+            self.translation_event_boxes.as_deref().unwrap_or_default(),
+            new.translation_event_boxes.as_deref().unwrap_or_default(),
+            ObjectRef::TranslationEventBox,
+            &mut objects,
+        );
+
+        let mut fields = Vec::new();
+        diff_values(
+            String::new(),
+            &get_difficulty_fields(self),
+            &get_difficulty_fields(new),
+            &mut fields,
+        );
+
+        DifficultyDiff { fields, objects }
+    }
+}
+
+impl Beatmap {
+    /// Compares the info file to an updated version of itself, returning every serialized field that changed.
+    pub fn diff(&self, new: &Beatmap) -> FieldDiff {
+        diff_serialized(self, new)
+    }
+}
+
+impl DifficultyInfo {
+    /// Compares the difficulty info to an updated version of itself, returning every serialized field that changed.
+    pub fn diff(&self, new: &DifficultyInfo) -> FieldDiff {
+        diff_serialized(self, new)
+    }
+}
+
+/// An object's beat and serialized fields.
+struct Entry {
+    beat: f32,
+    value: Value,
+}
+
+fn get_entries<T: Timed + Serialize>(objects: &[T]) -> Vec<Entry> {
+    objects
+        .iter()
+        .map(|object| Entry {
+            beat: object.get_beat(),
+            value: to_value(object),
+        })
+        .collect()
+}
+
+/// FX boxes are serialized individually, as they do not implement [`Serialize`] on their own.
+fn get_fx_entries(container: &Option<FxEventContainer>) -> Vec<Entry> {
+    let event_boxes = container.as_deref().map(Vec::as_slice).unwrap_or_default();
+    event_boxes
+        .iter()
+        .map(|event_box| Entry {
+            beat: event_box.beat,
+            value: to_value(&FxEventContainer {
+                event_boxes: vec![event_box.clone()],
+            }),
+        })
+        .collect()
+}
+
+fn get_difficulty_fields(difficulty: &Difficulty) -> Value {
+    let mut fields = Map::new();
+    fields.insert("version".to_string(), to_value(&difficulty.version));
+    fields.insert(
+        "basicEventTypesWithKeywords".to_string(),
+        to_value(&difficulty.special_events),
+    );
+    fields.insert(
+        "useNormalEventsAsCompatibleEvents".to_string(),
+        to_value(&difficulty.use_compatible_events),
+    );
+    Value::Object(fields)
+}
+
+fn to_value(value: &impl Serialize) -> Value {
+    serde_json::to_value(value).expect("Map types serialize into valid JSON")
+}
+
+fn diff_serialized(old: &impl Serialize, new: &impl Serialize) -> FieldDiff {
+    let mut fields = Vec::new();
+    diff_values(String::new(), &to_value(old), &to_value(new), &mut fields);
+    FieldDiff { fields }
+}
+
+/// Recursively compares two values, adding a change for every differing leaf field.
+fn diff_values(path: String, old: &Value, new: &Value, changes: &mut Vec<FieldChange>) {
+    let join = |key: &str| match path.is_empty() {
+        true => key.to_string(),
+        false => format!("{path}/{key}"),
+    };
+
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                diff_values(
+                    join(key),
+                    old_value,
+                    new.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+            for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                diff_values(join(key), &Value::Null, new_value, changes);
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                let old_value = old.get(i).unwrap_or(&Value::Null);
+                let new_value = new.get(i).unwrap_or(&Value::Null);
+                diff_values(join(&i.to_string()), old_value, new_value, changes);
+            }
+        }
+        (old, new) if old != new => changes.push(FieldChange {
+            field: path,
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn diff_list<T: Timed + Serialize>(
+    old: &[T],
+    new: &[T],
+    get_ref: fn(usize) -> ObjectRef,
+    changes: &mut Vec<ObjectChange>,
+) {
+    diff_entries(get_entries(old), get_entries(new), get_ref, changes);
+}
+
+fn diff_entries(
+    old: Vec<Entry>,
+    new: Vec<Entry>,
+    get_ref: fn(usize) -> ObjectRef,
+    changes: &mut Vec<ObjectChange>,
+) {
+    let mut old_matched = vec![false; old.len()];
+    let mut new_matched = vec![false; new.len()];
+    let mut list_changes = Vec::new();
+
+    // Identical objects.
+    let mut unmatched = group_unmatched(&new, &new_matched, |value| value.to_string());
+    for (i, entry) in old.iter().enumerate() {
+        if let Some(j) = unmatched
+            .get_mut(&entry.value.to_string())
+            .and_then(VecDeque::pop_front)
+        {
+            old_matched[i] = true;
+            new_matched[j] = true;
+        }
+    }
+
+    // Objects at the same beat and position.
+    let get_identity = |value: &Value| filter_fields(value, |key| IDENTITY_FIELDS.contains(&key));
+    let mut unmatched = group_unmatched(&new, &new_matched, get_identity);
+    for (i, entry) in old.iter().enumerate() {
+        if old_matched[i] {
+            continue;
+        }
+        if let Some(j) = unmatched
+            .get_mut(&get_identity(&entry.value))
+            .and_then(VecDeque::pop_front)
+        {
+            old_matched[i] = true;
+            new_matched[j] = true;
+
+            let mut fields = Vec::new();
+            diff_values(String::new(), &entry.value, &new[j].value, &mut fields);
+            list_changes.push(ObjectChange::Changed {
+                old: get_ref(i),
+                new: get_ref(j),
+                beat: new[j].beat,
+                fields,
+            });
+        }
+    }
+
+    // Objects that only changed beat or position.
+    let get_content = |value: &Value| filter_fields(value, |key| !POSITION_FIELDS.contains(&key));
+    let unmatched = group_unmatched(&new, &new_matched, get_content);
+    for (i, entry) in old.iter().enumerate() {
+        if old_matched[i] {
+            continue;
+        }
+        let nearest = unmatched
+            .get(&get_content(&entry.value))
+            .into_iter()
+            .flatten()
+            .filter(|j| !new_matched[**j] && (new[**j].beat - entry.beat).abs() <= MOVE_WINDOW)
+            .min_by(|a, b| {
+                let a = (new[**a].beat - entry.beat).abs();
+                let b = (new[**b].beat - entry.beat).abs();
+                a.total_cmp(&b)
+            });
+
+        if let Some(&j) = nearest {
+            old_matched[i] = true;
+            new_matched[j] = true;
+            list_changes.push(ObjectChange::Moved {
+                old: get_ref(i),
+                new: get_ref(j),
+                old_beat: entry.beat,
+                new_beat: new[j].beat,
+            });
+        }
+    }
+
+    for (i, entry) in old.iter().enumerate().filter(|(i, _)| !old_matched[*i]) {
+        list_changes.push(ObjectChange::Removed {
+            old: get_ref(i),
+            beat: entry.beat,
+        });
+    }
+    for (j, entry) in new.iter().enumerate().filter(|(j, _)| !new_matched[*j]) {
+        list_changes.push(ObjectChange::Added {
+            new: get_ref(j),
+            beat: entry.beat,
+        });
+    }
+
+    list_changes.sort_by(|a, b| a.get_beat().total_cmp(&b.get_beat()));
+    changes.extend(list_changes);
+}
+
+/// Groups the indices of unmatched entries by a key, in order.
+fn group_unmatched(
+    entries: &[Entry],
+    matched: &[bool],
+    get_key: impl Fn(&Value) -> String,
+) -> HashMap<String, VecDeque<usize>> {
+    let mut groups: HashMap<String, VecDeque<usize>> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate().filter(|(i, _)| !matched[*i]) {
+        groups
+            .entry(get_key(&entry.value))
+            .or_default()
+            .push_back(i);
+    }
+    groups
+}
+
+/// Returns the serialized fields of an object that match a filter, as a JSON string.
+fn filter_fields(value: &Value, filter: impl Fn(&str) -> bool) -> String {
+    match value {
+        Value::Object(map) => {
+            let filtered: Map<String, Value> = map
+                .iter()
+                .filter(|(key, _)| filter(key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            Value::Object(filtered).to_string()
+        }
+        other => other.to_string(),
+    }
+}
+
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.old, self.new)
+    }
+}
+
+impl Display for ObjectChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjectChange::Added { new, beat } => write!(f, "+ {new:?} at beat {beat}"),
+            ObjectChange::Removed { old, beat } => write!(f, "- {old:?} at beat {beat}"),
+            ObjectChange::Moved {
+                old,
+                new,
+                old_beat,
+                new_beat,
+            } => write!(
+                f,
+                "> {old:?} at beat {old_beat} moved to {new:?} at beat {new_beat}"
+            ),
+            ObjectChange::Changed {
+                old,
+                new,
+                beat,
+                fields,
+            } => {
+                write!(f, "~ {old:?} changed to {new:?} at beat {beat}")?;
+                for field in fields {
+                    write!(f, "\n    {field}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for DifficultyDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No changes");
+        }
+
+        let lines = (self.fields.iter().map(|field| format!("~ {field}")))
+            .chain(self.objects.iter().map(ObjectChange::to_string));
+        for (i, line) in lines.enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for FieldDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.fields.is_empty() {
+            return write!(f, "No changes");
+        }
+
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "~ {field}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::playfield::{Arc, CutDirection, Note, Wall};
+
+    fn get_note(beat: f32, col: i32) -> Note {
+        Note {
+            beat,
+            col,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unchanged() {
+        let difficulty = Difficulty {
+            notes: vec![get_note(1.0, 0), get_note(2.0, 1)],
+            ..Default::default()
+        };
+
+        let diff = difficulty.diff(&difficulty);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No changes");
+    }
+
+    #[test]
+    fn object_changes() {
+        let old = Difficulty {
+            notes: vec![get_note(1.0, 0), get_note(2.0, 1), get_note(3.0, 2)],
+            walls: vec![Wall::default()],
+            ..Default::default()
+        };
+        let new = Difficulty {
+            notes: vec![
+                get_note(1.0, 0),
+                Note {
+                    direction: CutDirection::Down,
+                    ..get_note(2.0, 1)
+                },
+                get_note(3.5, 3),
+            ],
+            walls: vec![],
+            ..Default::default()
+        };
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.objects,
+            vec![
+                ObjectChange::Changed {
+                    old: ObjectRef::Note(1),
+                    new: ObjectRef::Note(1),
+                    beat: 2.0,
+                    fields: vec![FieldChange {
+                        field: "d".to_string(),
+                        old: Value::from(0),
+                        new: Value::from(1),
+                    }],
+                },
+                ObjectChange::Moved {
+                    old: ObjectRef::Note(2),
+                    new: ObjectRef::Note(2),
+                    old_beat: 3.0,
+                    new_beat: 3.5,
+                },
+                ObjectChange::Removed {
+                    old: ObjectRef::Wall(0),
+                    beat: 0.0,
+                },
+            ]
+        );
+
+        assert_eq!(
+            diff.to_string(),
+            "~ Note(1) changed to Note(1) at beat 2\n    d: 0 -> 1\n\
+             > Note(2) at beat 3 moved to Note(2) at beat 3.5\n\
+             - Wall(0) at beat 0"
+        );
+    }
+
+    #[test]
+    fn moved_arc() {
+        let old = Difficulty {
+            arcs: vec![Arc {
+                beat: 1.0,
+                tail_beat: 2.0,
+                tail_col: 1,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let new = Difficulty {
+            arcs: vec![Arc {
+                beat: 1.5,
+                col: 1,
+                tail_beat: 2.5,
+                tail_col: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.objects,
+            vec![ObjectChange::Moved {
+                old: ObjectRef::Arc(0),
+                new: ObjectRef::Arc(0),
+                old_beat: 1.0,
+                new_beat: 1.5,
+            }]
+        );
+    }
+
+    #[test]
+    fn far_moves_are_replacements() {
+        let old = Difficulty {
+            notes: vec![get_note(1.0, 0)],
+            ..Default::default()
+        };
+        let new = Difficulty {
+            notes: vec![get_note(8.0, 0)],
+            ..Default::default()
+        };
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.objects.len(), 2);
+        assert!(matches!(diff.objects[0], ObjectChange::Removed { .. }));
+        assert!(matches!(diff.objects[1], ObjectChange::Added { .. }));
+    }
+
+    #[test]
+    fn info_fields() {
+        let old = DifficultyInfo::default();
+        let new = DifficultyInfo {
+            njs: 18.0,
+            ..Default::default()
+        };
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.to_string(), "~ _noteJumpMovementSpeed: 0.0 -> 18.0");
+        assert_eq!(
+            serde_json::to_string(&diff).unwrap(),
+            r#"{"fields":[{"field":"_noteJumpMovementSpeed","old":0.0,"new":18.0}]}"#
+        );
+    }
+}
@@ -1,16 +1,55 @@
 //! Defines the structure of a map's difficulty file(s) (i.e. `ExpertStandard.dat`).
 
+pub mod bpm;
+pub mod canonical;
 pub mod gameplay_event;
+pub mod lane_rotation;
 pub mod lightshow;
+pub mod merge;
+pub mod mirror;
+pub mod parity;
 pub mod playfield;
+pub mod ranking;
+pub mod retime;
+pub mod score;
+pub mod slice;
+pub mod snap;
+pub mod stats;
+pub mod time_index;
+pub mod validate;
 
+#[doc(hidden)]
+pub use bpm::*;
+#[doc(hidden)]
+pub use canonical::*;
 #[doc(hidden)]
 pub use gameplay_event::*;
 #[doc(hidden)]
+pub use lane_rotation::*;
+#[doc(hidden)]
 pub use lightshow::*;
 #[doc(hidden)]
+pub use merge::*;
+#[doc(hidden)]
+pub use mirror::*;
+#[doc(hidden)]
+pub use parity::*;
+#[doc(hidden)]
 pub use playfield::*;
+#[doc(hidden)]
+pub use ranking::*;
+#[doc(hidden)]
+pub use score::*;
+#[doc(hidden)]
+pub use snap::*;
+#[doc(hidden)]
+pub use stats::*;
+#[doc(hidden)]
+pub use time_index::*;
+#[doc(hidden)]
+pub use validate::*;
 
+use crate::timing_traits::{Duration, DurationMut, Timed, TimedMut};
 use serde::{Deserialize, Serialize};
 
 /// A map's difficulty file(s) (i.e. `ExpertStandard.dat`).
@@ -58,3 +97,321 @@ pub struct Difficulty {
     #[serde(rename = "useNormalEventsAsCompatibleEvents")]
     pub use_compatible_events: bool,
 }
+
+/// A reference to an object in a [`Difficulty`], by its index in the relevant list.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum ObjectRef {
+    BpmEvent(usize),
+    LaneRotationEvent(usize),
+    Note(usize),
+    Bomb(usize),
+    Wall(usize),
+    Arc(usize),
+    Chain(usize),
+    Waypoint(usize),
+    BasicEvent(usize),
+    ColorBoostEvent(usize),
+    ColorEventBox(usize),
+    RotationEventBox(usize),
+    /// An index into [`FxEventContainer::event_boxes`].
+    FxEventBox(usize),
+    TranslationEventBox(usize),
+}
+
+/// A reference to any object in a [`Difficulty`], returned by [`Difficulty::iter_timed`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimedObject<'a> {
+    BpmEvent(&'a BpmEvent),
+    LaneRotationEvent(&'a LaneRotationEvent),
+    Note(&'a Note),
+    Bomb(&'a Bomb),
+    Wall(&'a Wall),
+    Arc(&'a Arc),
+    Chain(&'a Chain),
+    Waypoint(&'a Waypoint),
+    BasicEvent(&'a BasicEvent),
+    ColorBoostEvent(&'a ColorBoostEvent),
+    ColorEventBox(&'a ColorEventBox),
+    RotationEventBox(&'a RotationEventBox),
+    FxEventBox(&'a FxEventBox),
+    TranslationEventBox(&'a TranslationEventBox),
+}
+
+/// A mutable reference to any object in a [`Difficulty`], returned by [`Difficulty::iter_timed_mut`].
+#[derive(Debug, PartialEq)]
+pub enum TimedObjectMut<'a> {
+    BpmEvent(&'a mut BpmEvent),
+    LaneRotationEvent(&'a mut LaneRotationEvent),
+    Note(&'a mut Note),
+    Bomb(&'a mut Bomb),
+    Wall(&'a mut Wall),
+    Arc(&'a mut Arc),
+    Chain(&'a mut Chain),
+    Waypoint(&'a mut Waypoint),
+    BasicEvent(&'a mut BasicEvent),
+    ColorBoostEvent(&'a mut ColorBoostEvent),
+    ColorEventBox(&'a mut ColorEventBox),
+    RotationEventBox(&'a mut RotationEventBox),
+    FxEventBox(&'a mut FxEventBox),
+    TranslationEventBox(&'a mut TranslationEventBox),
+}
+
+/// Calls an expression with the object inside of any [`TimedObject`] or [`TimedObjectMut`] variant.
+macro_rules! with_object {
+    ($enum:ident, $value:expr, $object:ident => $body:expr) => {
+        match $value {
+            $enum::BpmEvent($object) => $body,
+            $enum::LaneRotationEvent($object) => $body,
+            $enum::Note($object) => $body,
+            $enum::Bomb($object) => $body,
+            $enum::Wall($object) => $body,
+            $enum::Arc($object) => $body,
+            $enum::Chain($object) => $body,
+            $enum::Waypoint($object) => $body,
+            $enum::BasicEvent($object) => $body,
+            $enum::ColorBoostEvent($object) => $body,
+            $enum::ColorEventBox($object) => $body,
+            $enum::RotationEventBox($object) => $body,
+            $enum::FxEventBox($object) => $body,
+            $enum::TranslationEventBox($object) => $body,
+        }
+    };
+}
+
+/// Chains every list of objects in a difficulty, wrapping each object in a [`TimedObject`] or [`TimedObjectMut`].
+macro_rules! iter_objects {
+    ($enum:ident, $iter:ident, $difficulty:ident) => {
+        iter_objects!(
+            @chain $enum,
+            BpmEvent: $difficulty.bpm_events.$iter(),
+            LaneRotationEvent: $difficulty.lane_rotation_events.$iter(),
+            Note: $difficulty.notes.$iter(),
+            Bomb: $difficulty.bombs.$iter(),
+            Wall: $difficulty.walls.$iter(),
+            Arc: $difficulty.arcs.$iter(),
+            Chain: $difficulty.chains.$iter(),
+            Waypoint: $difficulty.waypoints.$iter(),
+            BasicEvent: $difficulty.basic_events.$iter(),
+            ColorBoostEvent: $difficulty.color_boost_events.$iter(),
+            ColorEventBox: $difficulty.color_event_boxes.$iter(),
+            RotationEventBox: $difficulty.rotation_event_boxes.$iter(),
+            FxEventBox: ($difficulty.fx_event_boxes.$iter()).flat_map(|container| container.$iter()),
+            TranslationEventBox: $difficulty.translation_event_boxes.$iter().flatten(),
+        )
+    };
+    (@chain $enum:ident, $($variant:ident: $objects:expr,)*) => {
+        std::iter::empty()
+            $(.chain(
+                ($objects)
+                    .enumerate()
+                    .map(|(i, object)| (ObjectRef::$variant(i), $enum::$variant(object))),
+            ))*
+    };
+}
+
+impl<'a> TimedObject<'a> {
+    /// Returns the object as a [`Timed`], which every object implements.
+    pub fn as_timed(&self) -> &'a dyn Timed {
+        with_object!(TimedObject, *self, object => object)
+    }
+
+    /// Returns the object as a [`Duration`], if it is a wall, arc or chain.
+    pub fn as_duration(&self) -> Option<&'a dyn Duration> {
+        match *self {
+            TimedObject::Wall(wall) => Some(wall),
+            TimedObject::Arc(arc) => Some(arc),
+            TimedObject::Chain(chain) => Some(chain),
+            _ => None,
+        }
+    }
+}
+
+impl Timed for TimedObject<'_> {
+    fn get_beat(&self) -> f32 {
+        self.as_timed().get_beat()
+    }
+}
+
+impl TimedObjectMut<'_> {
+    /// Returns the object as a [`Timed`], which every object implements.
+    pub fn as_timed(&self) -> &dyn Timed {
+        with_object!(TimedObjectMut, self, object => *object)
+    }
+
+    /// Returns the object as a [`TimedMut`], which every object implements.
+    pub fn as_timed_mut(&mut self) -> &mut dyn TimedMut {
+        with_object!(TimedObjectMut, self, object => *object)
+    }
+
+    /// Returns the object as a [`DurationMut`], if it is a wall, arc or chain.
+    pub fn as_duration_mut(&mut self) -> Option<&mut dyn DurationMut> {
+        match self {
+            TimedObjectMut::Wall(wall) => Some(*wall),
+            TimedObjectMut::Arc(arc) => Some(*arc),
+            TimedObjectMut::Chain(chain) => Some(*chain),
+            _ => None,
+        }
+    }
+}
+
+impl Timed for TimedObjectMut<'_> {
+    fn get_beat(&self) -> f32 {
+        self.as_timed().get_beat()
+    }
+}
+
+impl TimedMut for TimedObjectMut<'_> {
+    fn set_beat(&mut self, beat: f32) {
+        self.as_timed_mut().set_beat(beat);
+    }
+}
+
+impl Difficulty {
+    /// Returns every object and event in the difficulty, along with a reference to its position in the relevant list.
+    ///
+    /// Objects are returned one list at a time, rather than sorted by beat.
+    /// For sorted queries, see [`TimeIndex`].
+    pub fn iter_timed(&self) -> impl Iterator<Item = (ObjectRef, TimedObject<'_>)> {
+        iter_objects!(TimedObject, iter, self)
+    }
+
+    /// Returns every object and event in the difficulty mutably, along with a reference to its position in the relevant list.
+    ///
+    /// Objects are returned one list at a time, rather than sorted by beat.
+    pub fn iter_timed_mut(&mut self) -> impl Iterator<Item = (ObjectRef, TimedObjectMut<'_>)> {
+        iter_objects!(TimedObjectMut, iter_mut, self)
+    }
+
+    /// Replaces the beat of every object with the result of a function,
+    /// including the end beats of walls, arcs and chains.
+    ///
+    /// Beats that are relative to another object, such as the offsets of event box data, are left unchanged.
+    pub(crate) fn map_beats(&mut self, mut f: impl FnMut(ObjectRef, f32) -> f32) {
+        for (object, mut timed) in self.iter_timed_mut() {
+            match timed.as_duration_mut() {
+                Some(duration) => {
+                    let beat = f(object, duration.get_beat());
+                    let end_beat = f(object, duration.get_end_beat());
+                    duration.set_beat(beat);
+                    duration.set_end_beat(end_beat);
+                }
+                None => {
+                    let beat = f(object, timed.get_beat());
+                    timed.set_beat(beat);
+                }
+            }
+        }
+    }
+
+    /// Replaces the relative beats of every event box with the result of a function,
+    /// which is given the beat of the box and the relative beat.
+    ///
+    /// This includes the [beat offset](ColorEventData::beat_offset) of event data,
+    /// and the [beat distribution](ColorEventGroup::beat_dist_value) of event groups.
+    pub(crate) fn map_relative_beats(&mut self, mut f: impl FnMut(f32, f32) -> f32) {
+        macro_rules! map_groups {
+            ($event_box:expr) => {
+                for group in &mut $event_box.groups {
+                    group.beat_dist_value = f($event_box.beat, group.beat_dist_value);
+                    for data in &mut group.data {
+                        data.beat_offset = f($event_box.beat, data.beat_offset);
+                    }
+                }
+            };
+        }
+
+        for event_box in &mut self.color_event_boxes {
+            map_groups!(event_box);
+        }
+        for event_box in &mut self.rotation_event_boxes {
+            map_groups!(event_box);
+        }
+        for event_box in self
+            .fx_event_boxes
+            .iter_mut()
+            .flat_map(|container| container.iter_mut())
+        {
+            map_groups!(event_box);
+        }
+        for event_box in self.translation_event_boxes.iter_mut().flatten() {
+            map_groups!(event_box);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_beat_keeps_duration() {
+        let mut arc = Arc {
+            beat: 1.0,
+            tail_beat: 3.0,
+            ..Default::default()
+        };
+        arc.set_beat(4.0);
+        assert_eq!((arc.beat, arc.tail_beat), (4.0, 6.0));
+        arc.set_end_beat(5.0);
+        assert_eq!(arc.get_duration(), 1.0);
+
+        let mut wall = Wall {
+            beat: 1.0,
+            duration: 2.0,
+            ..Default::default()
+        };
+        wall.set_beat_f64(4.0);
+        assert_eq!(wall.get_end_beat_f64(), 6.0);
+        wall.set_end_beat(5.0);
+        assert_eq!(wall.duration, 1.0);
+        wall.set_duration_f64(0.5);
+        assert_eq!(wall.get_end_beat(), 4.5);
+    }
+
+    #[test]
+    fn iter_timed() {
+        let mut difficulty = Difficulty {
+            notes: vec![Note::default(), Note::default()],
+            walls: vec![Wall {
+                duration: 2.0,
+                ..Default::default()
+            }],
+            fx_event_boxes: Some(FxEventContainer {
+                event_boxes: vec![FxEventBox::default()],
+            }),
+            ..Default::default()
+        };
+
+        for (_, mut object) in difficulty.iter_timed_mut() {
+            let beat = object.get_beat();
+            object.set_beat(beat + 1.0);
+        }
+
+        let objects: Vec<ObjectRef> = difficulty.iter_timed().map(|(object, _)| object).collect();
+        assert_eq!(
+            objects,
+            vec![
+                ObjectRef::Note(0),
+                ObjectRef::Note(1),
+                ObjectRef::Wall(0),
+                ObjectRef::FxEventBox(0),
+            ]
+        );
+        assert!(
+            difficulty
+                .iter_timed()
+                .all(|(_, object)| object.get_beat() == 1.0)
+        );
+        assert_eq!(difficulty.walls[0].duration, 2.0);
+
+        let durations = difficulty
+            .iter_timed()
+            .filter_map(|(_, object)| object.as_duration());
+        assert_eq!(durations.count(), 1);
+    }
+}
@@ -0,0 +1,188 @@
+//! Converts between beats and seconds, using a difficulty's [`BpmEvent`]s.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::gameplay_event::BpmEvent;
+
+/// Converts between beats and seconds, accounting for a difficulty's [`BpmEvent`]s.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct BpmTimeline {
+    /// The sections of the song that share the same BPM, sorted by beat.
+    ///
+    /// There will always be at least one region, starting at beat zero.
+    regions: Vec<BpmRegion>,
+}
+
+/// A section of the song that shares the same BPM.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct BpmRegion {
+    /// The beat that the region starts.
+    pub beat: f32,
+    /// The number of seconds from the start of the song to the start of the region.
+    pub seconds: f32,
+    /// The BPM of the region.
+    pub bpm: f32,
+}
+
+impl BpmRegion {
+    /// Returns the length of a single beat in this region, in seconds.
+    pub fn get_seconds_per_beat(&self) -> f32 {
+        60.0 / self.bpm
+    }
+}
+
+impl BpmTimeline {
+    /// Creates a timeline from the map's [base BPM](crate::info::Beatmap::bpm) and a list of [`BpmEvent`]s.
+    ///
+    /// The events do not need to be sorted. Events with a non-positive BPM are ignored.
+    pub fn new(base_bpm: f32, events: &[BpmEvent]) -> Self {
+        let mut events: Vec<&BpmEvent> = events
+            .iter()
+            .filter(|event| event.bpm > 0.0 && event.beat >= 0.0)
+            .collect();
+        events.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        let mut regions = vec![BpmRegion {
+            beat: 0.0,
+            seconds: 0.0,
+            bpm: base_bpm,
+        }];
+
+        for event in events {
+            let last = regions
+                .last_mut()
+                .expect("There is always at least one region");
+
+            if event.beat == last.beat {
+                last.bpm = event.bpm;
+                continue;
+            }
+
+            let seconds = last.seconds + (event.beat - last.beat) * last.get_seconds_per_beat();
+            regions.push(BpmRegion {
+                beat: event.beat,
+                seconds,
+                bpm: event.bpm,
+            });
+        }
+
+        Self { regions }
+    }
+
+    /// Returns the sections of the song that share the same BPM, sorted by beat.
+    pub fn get_regions(&self) -> &[BpmRegion] {
+        &self.regions
+    }
+
+    /// Returns the BPM region that contains a beat.
+    pub fn get_region(&self, beat: f32) -> &BpmRegion {
+        let index = self.regions.partition_point(|region| region.beat <= beat);
+        &self.regions[index.saturating_sub(1)]
+    }
+
+    /// Returns the BPM at a beat.
+    pub fn get_bpm(&self, beat: f32) -> f32 {
+        self.get_region(beat).bpm
+    }
+
+    /// Converts a beat into the number of seconds from the start of the song.
+    pub fn get_seconds(&self, beat: f32) -> f32 {
+        let region = self.get_region(beat);
+        region.seconds + (beat - region.beat) * region.get_seconds_per_beat()
+    }
+
+    /// Converts the number of seconds from the start of the song into a beat.
+    pub fn get_beat(&self, seconds: f32) -> f32 {
+        let index = self
+            .regions
+            .partition_point(|region| region.seconds <= seconds);
+        let region = &self.regions[index.saturating_sub(1)];
+        region.beat + (seconds - region.seconds) / region.get_seconds_per_beat()
+    }
+}
+
+impl Difficulty {
+    /// Returns a [`BpmTimeline`] for converting between beats and seconds.
+    ///
+    /// The base BPM is defined by the map's [info file](crate::info::Beatmap::bpm).
+    pub fn get_bpm_timeline(&self, base_bpm: f32) -> BpmTimeline {
+        BpmTimeline::new(base_bpm, &self.bpm_events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_bpm() {
+        let timeline = BpmTimeline::new(120.0, &[]);
+
+        assert_eq!(timeline.get_seconds(0.0), 0.0);
+        assert_eq!(timeline.get_seconds(4.0), 2.0);
+        assert_eq!(timeline.get_beat(2.0), 4.0);
+        assert_eq!(timeline.get_bpm(100.0), 120.0);
+    }
+
+    #[test]
+    fn bpm_change() {
+        let timeline = BpmTimeline::new(
+            120.0,
+            &[BpmEvent {
+                beat: 4.0,
+                bpm: 60.0,
+            }],
+        );
+
+        assert_eq!(timeline.get_seconds(4.0), 2.0);
+        assert_eq!(timeline.get_seconds(6.0), 4.0);
+        assert_eq!(timeline.get_beat(4.0), 6.0);
+        assert_eq!(timeline.get_bpm(3.9), 120.0);
+        assert_eq!(timeline.get_bpm(4.0), 60.0);
+    }
+
+    #[test]
+    fn event_at_zero_replaces_base() {
+        let timeline = BpmTimeline::new(
+            100.0,
+            &[BpmEvent {
+                beat: 0.0,
+                bpm: 60.0,
+            }],
+        );
+
+        assert_eq!(timeline.get_regions().len(), 1);
+        assert_eq!(timeline.get_seconds(2.0), 2.0);
+    }
+
+    #[test]
+    fn unsorted_events() {
+        let timeline = BpmTimeline::new(
+            60.0,
+            &[
+                BpmEvent {
+                    beat: 8.0,
+                    bpm: 240.0,
+                },
+                BpmEvent {
+                    beat: 4.0,
+                    bpm: 120.0,
+                },
+            ],
+        );
+
+        assert_eq!(timeline.get_seconds(4.0), 4.0);
+        assert_eq!(timeline.get_seconds(8.0), 6.0);
+        assert_eq!(timeline.get_seconds(12.0), 7.0);
+        assert_eq!(timeline.get_beat(7.0), 12.0);
+    }
+}
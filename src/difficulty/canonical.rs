@@ -0,0 +1,340 @@
+//! Sorts and serializes difficulties in a stable form, so that logically equal maps produce byte-identical files.
+//!
+//! Editors write objects in different orders and with different floating-point noise,
+//! which makes version control diffs of `.dat` files hard to read.
+//! [`Difficulty::canonicalize`] fixes the order of objects, and [`CanonicalFormatter`] fixes the formatting of numbers.
+//!
+//! # Example
+//! ```
+//! # use bsru::*;
+//! let mut difficulty = Difficulty::default();
+//! difficulty.canonicalize();
+//! let json = to_canonical_string_pretty(&difficulty).unwrap();
+//! ```
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::lightshow::FxEventContainer;
+use serde::Serialize;
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter};
+use serde_json::{Number, Serializer, Value};
+use std::cmp::Ordering;
+use std::io;
+
+/// The number of divisions per unit (i.e. per beat) of the grid that [`CanonicalFormatter`] snaps floats to.
+///
+/// This is divisible by every common beat subdivision (such as 1/64, 1/3 and 1/5).
+pub const CANONICAL_DIVISIONS: f64 = 1920.0;
+/// The minimum distance that [`CanonicalFormatter`] will snap a float to the [grid](CANONICAL_DIVISIONS).
+///
+/// Larger values can be snapped by up to one `f32` step (i.e. `1.0000001` or `1233.9999`),
+/// so only floating-point noise is removed, and values further from the grid are unchanged.
+pub const CANONICAL_TOLERANCE: f64 = 0.000001;
+
+/// The serialized fields used to sort objects on the same beat, in order of priority.
+const SORT_FIELDS: [&str; 7] = ["x", "y", "c", "d", "et", "g", "i"];
+
+impl Difficulty {
+    /// Sorts every list of objects by beat, and then by position, color, direction, event type and group ID.
+    ///
+    /// Objects that share all of these are sorted by the rest of their fields,
+    /// so the result does not depend on the original order. Special event keywords are sorted by name.
+    pub fn canonicalize(&mut self) {
+        sort_canonical(&mut self.bpm_events, to_value);
+        sort_canonical(&mut self.lane_rotation_events, to_value);
+        sort_canonical(&mut self.notes, to_value);
+        sort_canonical(&mut self.bombs, to_value);
+        sort_canonical(&mut self.walls, to_value);
+        sort_canonical(&mut self.arcs, to_value);
+        sort_canonical(&mut self.chains, to_value);
+        sort_canonical(&mut self.waypoints, to_value);
+        sort_canonical(&mut self.basic_events, to_value);
+        sort_canonical(&mut self.color_boost_events, to_value);
+        sort_canonical(&mut self.color_event_boxes, to_value);
+        sort_canonical(&mut self.rotation_event_boxes, to_value);
+        if let Some(container) = &mut self.fx_event_boxes {
+            // FX boxes can only be serialized as part of a container.
+            sort_canonical(&mut container.event_boxes, |event_box| {
+                to_value(&FxEventContainer {
+                    event_boxes: vec![event_box.clone()],
+                })
+            });
+        }
+        if let Some(event_boxes) = &mut self.translation_event_boxes {
+            sort_canonical(event_boxes, to_value);
+        }
+
+        if let Some(keywords) = &mut self.special_events.keywords {
+            keywords.sort_by(|a, b| a.keyword.cmp(&b.keyword));
+        }
+    }
+}
+
+/// Serializes a value into compact JSON, formatting floats using [`CanonicalFormatter`].
+pub fn to_canonical_string(value: &impl Serialize) -> serde_json::Result<String> {
+    to_string_with(value, CanonicalFormatter::new())
+}
+
+/// Serializes a value into pretty-printed JSON, formatting floats using [`CanonicalFormatter`].
+pub fn to_canonical_string_pretty(value: &impl Serialize) -> serde_json::Result<String> {
+    to_string_with(value, CanonicalFormatter::pretty())
+}
+
+fn to_string_with(value: &impl Serialize, formatter: impl Formatter) -> serde_json::Result<String> {
+    let mut bytes = Vec::new();
+    value.serialize(&mut Serializer::with_formatter(&mut bytes, formatter))?;
+    Ok(String::from_utf8(bytes).expect("JSON output is valid UTF-8"))
+}
+
+/// A JSON formatter that snaps floats that are within [`CANONICAL_TOLERANCE`] (or one `f32` step)
+/// of the [grid](CANONICAL_DIVISIONS) onto it, and removes negative zeros.
+///
+/// Everything else is delegated to an inner formatter, which is compact by default.
+/// Can be used with [`serde_json::Serializer::with_formatter`].
+#[derive(Default, Debug, Clone)]
+pub struct CanonicalFormatter<F = CompactFormatter> {
+    inner: F,
+}
+
+impl CanonicalFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CanonicalFormatter<PrettyFormatter<'_>> {
+    pub fn pretty() -> Self {
+        Self::with_formatter(PrettyFormatter::new())
+    }
+}
+
+impl<F: Formatter> CanonicalFormatter<F> {
+    pub fn with_formatter(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+fn round_canonical(value: f64) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        // Also removes negative zeros.
+        return value.abs();
+    }
+
+    let snapped = (value * CANONICAL_DIVISIONS).round() / CANONICAL_DIVISIONS;
+    let tolerance = CANONICAL_TOLERANCE.max(value.abs() * f32::EPSILON as f64);
+    if (value - snapped).abs() <= tolerance {
+        // Small negative values can snap to a negative zero.
+        snapped + 0.0
+    } else {
+        value
+    }
+}
+
+/// Rounds every float in a value the same way as [`CanonicalFormatter`], so that sorting matches the output.
+fn round_value(value: &mut Value) {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            if let Some(rounded) = number
+                .as_f64()
+                .map(round_canonical)
+                .and_then(Number::from_f64)
+            {
+                *number = rounded;
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(round_value),
+        Value::Object(map) => map.values_mut().for_each(round_value),
+        _ => {}
+    }
+}
+
+impl<F: Formatter> Formatter for CanonicalFormatter<F> {
+    fn write_f32<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f32) -> io::Result<()> {
+        let rounded = round_canonical(value as f64) as f32;
+        self.inner.write_f32(writer, rounded)
+    }
+
+    fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        self.inner.write_f64(writer, round_canonical(value))
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_object_key(writer, first)
+    }
+
+    fn end_object_key<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object_key(writer)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object_value(writer)
+    }
+}
+
+fn to_value(value: &impl Serialize) -> Value {
+    serde_json::to_value(value).expect("Map types serialize into valid JSON")
+}
+
+/// Sorts objects by beat, then by [`SORT_FIELDS`], and then by their entire serialized value.
+///
+/// Floats are rounded before sorting, so objects that only differ by floating-point noise are sorted the same way.
+fn sort_canonical<T>(objects: &mut Vec<T>, serialize: impl Fn(&T) -> Value) {
+    let mut keyed: Vec<(Vec<Value>, T)> = objects
+        .drain(..)
+        .map(|object| {
+            let mut value = serialize(&object);
+            round_value(&mut value);
+            let mut key: Vec<Value> = (["b"].iter().chain(&SORT_FIELDS))
+                .map(|field| value.get(field).cloned().unwrap_or_default())
+                .collect();
+            key.push(Value::String(value.to_string()));
+            (key, object)
+        })
+        .collect();
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        (a.iter().zip(b))
+            .map(|(a, b)| compare_values(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    objects.extend(keyed.into_iter().map(|(_, object)| object));
+}
+
+/// Compares numbers numerically, and anything else using its JSON representation.
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::playfield::{Note, NoteColor};
+
+    fn get_note(beat: f32, col: i32, color: NoteColor) -> Note {
+        Note {
+            beat,
+            col,
+            color,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn order_independent() {
+        let notes = vec![
+            get_note(2.0, 0, NoteColor::Left),
+            get_note(1.0, 3, NoteColor::Right),
+            get_note(1.0, 10, NoteColor::Left),
+            get_note(1.0, 3, NoteColor::Left),
+        ];
+
+        let mut first = Difficulty {
+            notes: notes.clone(),
+            ..Default::default()
+        };
+        let mut second = Difficulty {
+            notes: notes.into_iter().rev().collect(),
+            ..Default::default()
+        };
+        first.canonicalize();
+        second.canonicalize();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            first.notes,
+            vec![
+                get_note(1.0, 3, NoteColor::Left),
+                get_note(1.0, 3, NoteColor::Right),
+                get_note(1.0, 10, NoteColor::Left),
+                get_note(2.0, 0, NoteColor::Left),
+            ]
+        );
+    }
+
+    #[test]
+    fn noisy_order() {
+        let mut noisy = Difficulty {
+            notes: vec![
+                get_note(1.0, 3, NoteColor::Left),
+                get_note(1.0000001, 0, NoteColor::Left),
+            ],
+            ..Default::default()
+        };
+        let mut clean = Difficulty {
+            notes: vec![
+                get_note(1.0, 0, NoteColor::Left),
+                get_note(1.0, 3, NoteColor::Left),
+            ],
+            ..Default::default()
+        };
+        noisy.canonicalize();
+        clean.canonicalize();
+
+        assert_eq!(
+            to_canonical_string(&noisy).unwrap(),
+            to_canonical_string(&clean).unwrap()
+        );
+    }
+
+    #[test]
+    fn float_noise() {
+        let noisy = vec![1.0000001_f32, 0.1 + 0.2, -0.0, 0.33333334, 1233.9999];
+        assert_eq!(
+            to_canonical_string(&noisy).unwrap(),
+            "[1.0,0.3,0.0,0.33333334,1234.0]"
+        );
+
+        // Exact values are unchanged, even if they aren't on the grid.
+        let exact = vec![1234.515625_f32, 0.123, 2.5e-5, 1.0e9];
+        assert_eq!(
+            to_canonical_string(&exact).unwrap(),
+            serde_json::to_string(&exact).unwrap()
+        );
+    }
+
+    #[test]
+    fn pretty() {
+        let value = vec![0.5_f32];
+        assert_eq!(to_canonical_string_pretty(&value).unwrap(), "[\n  0.5\n]");
+    }
+}
@@ -0,0 +1,188 @@
+//! Resolves the total lane rotation of objects in 90/360 degree difficulties, using [`LaneRotationEvent`]s.
+//!
+//! Rotations are cumulative, so each event adds its degrees to the total rotation of all following objects.
+//! The [`ExecutionTime`] decides if objects on the same beat as the event are rotated:
+//! early events affect objects *on or after* their beat, while late events only affect objects *after* their beat.
+//!
+//! Difficulty file V2 uses [basic events](BasicEvent) of type 14 (early) and 15 (late) instead,
+//! which are [converted](LaneRotationEvent::from_basic_event) and combined with the V3 events.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::gameplay_event::{ExecutionTime, LaneRotationEvent};
+use crate::difficulty::lightshow::BasicEvent;
+
+/// The [event type](BasicEvent::event_type) of an early V2 lane rotation event.
+pub const EARLY_ROTATION_EVENT_TYPE: i32 = 14;
+/// The [event type](BasicEvent::event_type) of a late V2 lane rotation event.
+pub const LATE_ROTATION_EVENT_TYPE: i32 = 15;
+/// The number of degrees for each [value](BasicEvent::value) of a V2 lane rotation event.
+const BASIC_ROTATION_DEGREES: [f32; 8] = [-60.0, -45.0, -30.0, -15.0, 15.0, 30.0, 45.0, 60.0];
+
+/// Resolves the total lane rotation at any beat, accounting for a difficulty's [`LaneRotationEvent`]s.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct LaneRotationTimeline {
+    /// The events, sorted by beat.
+    events: Vec<LaneRotationEvent>,
+    /// The total rotation after each event, including itself.
+    totals: Vec<f32>,
+}
+
+impl LaneRotationTimeline {
+    /// Creates a timeline from a list of [`LaneRotationEvent`]s, which do not need to be sorted.
+    pub fn new(events: &[LaneRotationEvent]) -> Self {
+        let mut events = events.to_vec();
+        events.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        let totals = events
+            .iter()
+            .scan(0.0, |total, event| {
+                *total += event.degrees;
+                Some(*total)
+            })
+            .collect();
+
+        Self { events, totals }
+    }
+
+    /// Returns the events used by the timeline, sorted by beat.
+    pub fn get_events(&self) -> &[LaneRotationEvent] {
+        &self.events
+    }
+
+    /// Returns the total rotation (in degrees) of an object at a beat.
+    ///
+    /// Positive values rotate clockwise (when looking down), towards the player's right.
+    pub fn get_rotation(&self, beat: f32) -> f32 {
+        let index = self.events.partition_point(|event| event.beat <= beat);
+        let Some(total) = index.checked_sub(1).map(|i| self.totals[i]) else {
+            return 0.0;
+        };
+
+        // Late events on this exact beat do not affect the object yet.
+        let late: f32 = self.events[..index]
+            .iter()
+            .rev()
+            .take_while(|event| event.beat == beat)
+            .filter(|event| event.execution_time == ExecutionTime::Late)
+            .map(|event| event.degrees)
+            .sum();
+
+        total - late
+    }
+}
+
+impl LaneRotationEvent {
+    /// Converts a V2 lane rotation event (type 14 or 15) into a [`LaneRotationEvent`].
+    ///
+    /// Returns `None` if the event is not a lane rotation event, or if its value is outside of the range 0..7 inclusive.
+    pub fn from_basic_event(event: &BasicEvent) -> Option<Self> {
+        let execution_time = match event.event_type {
+            EARLY_ROTATION_EVENT_TYPE => ExecutionTime::Early,
+            LATE_ROTATION_EVENT_TYPE => ExecutionTime::Late,
+            _ => return None,
+        };
+        let degrees = *BASIC_ROTATION_DEGREES.get(usize::try_from(event.value).ok()?)?;
+
+        Some(Self {
+            beat: event.beat,
+            execution_time,
+            degrees,
+        })
+    }
+}
+
+impl Difficulty {
+    /// Returns a [`LaneRotationTimeline`] for resolving the total lane rotation of objects.
+    ///
+    /// This includes both [`LaneRotationEvent`]s and V2 rotation events stored as [basic events](Difficulty::basic_events).
+    pub fn get_lane_rotation_timeline(&self) -> LaneRotationTimeline {
+        let basic_events = self
+            .basic_events
+            .iter()
+            .filter_map(LaneRotationEvent::from_basic_event);
+        let events: Vec<LaneRotationEvent> = self
+            .lane_rotation_events
+            .iter()
+            .cloned()
+            .chain(basic_events)
+            .collect();
+
+        LaneRotationTimeline::new(&events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_event(beat: f32, execution_time: ExecutionTime, degrees: f32) -> LaneRotationEvent {
+        LaneRotationEvent {
+            beat,
+            execution_time,
+            degrees,
+        }
+    }
+
+    #[test]
+    fn early_and_late() {
+        let timeline = LaneRotationTimeline::new(&[
+            get_event(4.0, ExecutionTime::Late, 15.0),
+            get_event(2.0, ExecutionTime::Early, 30.0),
+            get_event(4.0, ExecutionTime::Early, -45.0),
+        ]);
+
+        assert_eq!(timeline.get_rotation(0.0), 0.0);
+        assert_eq!(timeline.get_rotation(2.0), 30.0);
+        assert_eq!(timeline.get_rotation(3.9), 30.0);
+        // Only the early event affects objects on its beat.
+        assert_eq!(timeline.get_rotation(4.0), -15.0);
+        assert_eq!(timeline.get_rotation(4.1), 0.0);
+    }
+
+    #[test]
+    fn basic_events() {
+        let event = BasicEvent {
+            beat: 1.0,
+            event_type: LATE_ROTATION_EVENT_TYPE,
+            value: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            LaneRotationEvent::from_basic_event(&event),
+            Some(get_event(1.0, ExecutionTime::Late, -30.0))
+        );
+
+        let invalid = BasicEvent { value: 8, ..event };
+        assert_eq!(LaneRotationEvent::from_basic_event(&invalid), None);
+
+        let lighting = BasicEvent {
+            event_type: 1,
+            value: 2,
+            ..Default::default()
+        };
+        assert_eq!(LaneRotationEvent::from_basic_event(&lighting), None);
+    }
+
+    #[test]
+    fn unified_timeline() {
+        let difficulty = Difficulty {
+            lane_rotation_events: vec![get_event(1.0, ExecutionTime::Early, 15.0)],
+            basic_events: vec![BasicEvent {
+                beat: 2.0,
+                event_type: EARLY_ROTATION_EVENT_TYPE,
+                value: 7,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let timeline = difficulty.get_lane_rotation_timeline();
+        assert_eq!(timeline.get_events().len(), 2);
+        assert_eq!(timeline.get_rotation(2.0), 75.0);
+    }
+}
@@ -1,18 +1,24 @@
 //! Events that have no effect on gameplay.
 
 pub mod basic;
+pub mod convert;
 pub mod easing;
 pub mod filter;
 pub mod group;
+pub mod photosensitivity;
 
 #[doc(hidden)]
 pub use basic::*;
 #[doc(hidden)]
+pub use convert::*;
+#[doc(hidden)]
 pub use easing::*;
 #[doc(hidden)]
 pub use filter::*;
 #[doc(hidden)]
 pub use group::*;
+#[doc(hidden)]
+pub use photosensitivity::*;
 
 use loose_enum::loose_enum;
 
@@ -34,7 +40,7 @@ loose_enum! {
 }
 
 impl DistributionType {
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     #[allow(deprecated)]
     fn compute_beat_offset(
         &self,
@@ -64,7 +70,7 @@ impl DistributionType {
         )
     }
 
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     #[allow(deprecated)]
     fn compute_value_offset(
         &self,
@@ -94,7 +100,7 @@ impl DistributionType {
         )
     }
 
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     #[allow(deprecated)]
     #[inline(always)]
     fn compute_offset(
@@ -0,0 +1,440 @@
+//! Converts V2-style lighting, made entirely of [`BasicEvent`]s, into V3 group lighting.
+//!
+//! Light events become [`ColorEventBox`]es with the same color, brightness and transition.
+//! V3 events have no flash or fade effect, so these are approximated using a second event
+//! that returns to normal brightness after [`FLASH_BEATS`], or turns off after [`FADE_BEATS`].
+//!
+//! Ring spin and laser speed events become [`RotationEventBox`] approximations:
+//! - Ring spins rotate the rings by [`RING_SPIN_DEGREES`] over [`RING_SPIN_BEATS`].
+//! - Laser speeds rotate the lasers continuously until the next speed event of the same type
+//!   (or the end of the map), at [`LASER_DEGREES_PER_BEAT`] for each unit of speed. A speed of zero resets the lasers.
+//!
+//! Any other event (such as ring zooms), or event types without a group, are left unchanged.
+//!
+//! Each V3 environment has its own groups, which don't match the basic event types,
+//! so the group that each event type is converted into must be given in [`LightConversionOptions`].
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::lightshow::basic::BasicEvent;
+use crate::difficulty::lightshow::easing::Easing;
+use crate::difficulty::lightshow::group::color::{
+    ColorEventBox, ColorEventData, ColorEventGroup, ColorTransitionType, LightColor,
+};
+use crate::difficulty::lightshow::group::rotation::{
+    RotationDirection, RotationEventBox, RotationEventData, RotationEventGroup,
+};
+use crate::difficulty::lightshow::photosensitivity::get_last_beat;
+use crate::difficulty::lightshow::{EventAxis, TransitionType};
+use crate::difficulty::slice::splice_sorted;
+use std::collections::HashMap;
+
+/// The number of beats a converted flash takes to return to normal brightness.
+pub const FLASH_BEATS: f32 = 0.5;
+/// The brightness of a converted flash, relative to its normal brightness.
+pub const FLASH_BRIGHTNESS: f32 = 1.2;
+/// The number of beats a converted fade takes to turn off.
+pub const FADE_BEATS: f32 = 1.0;
+/// The number of degrees that each converted ring spin rotates the rings by.
+pub const RING_SPIN_DEGREES: f32 = 90.0;
+/// The number of beats that each converted ring spin takes.
+pub const RING_SPIN_BEATS: f32 = 1.0;
+/// The number of degrees that converted lasers rotate each beat, for each unit of speed.
+pub const LASER_DEGREES_PER_BEAT: f32 = 30.0;
+
+const RING_SPIN_EVENT_TYPE: i32 = 8;
+const LEFT_LASER_SPEED_EVENT_TYPE: i32 = 12;
+const RIGHT_LASER_SPEED_EVENT_TYPE: i32 = 13;
+
+/// Controls which basic events are [converted](Difficulty::convert_basic_lighting) into group lighting.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct LightConversionOptions {
+    /// The event type of each light event to convert, and the group ID of the lights it controls.
+    pub color_groups: Vec<(i32, i32)>,
+    /// The event type of each ring spin (type 8) and laser speed (types 12 and 13) event to convert,
+    /// and the group ID of the objects it rotates.
+    pub rotation_groups: Vec<(i32, i32)>,
+    /// If true, converted basic events will be removed.
+    pub remove_converted: bool,
+}
+
+impl Default for LightConversionOptions {
+    /// Converts nothing until groups are added.
+    fn default() -> Self {
+        Self {
+            color_groups: Vec::new(),
+            rotation_groups: Vec::new(),
+            remove_converted: true,
+        }
+    }
+}
+
+impl Difficulty {
+    /// Converts light, ring spin and laser speed [basic events](Difficulty::basic_events) into event boxes,
+    /// using the groups given in the options.
+    ///
+    /// For details, see the [module docs](crate::difficulty::lightshow::convert).
+    pub fn convert_basic_lighting(&mut self, options: &LightConversionOptions) {
+        let mut order: Vec<usize> = (0..self.basic_events.len()).collect();
+        order.sort_by(|a, b| {
+            let a = self.basic_events[*a].beat;
+            let b = self.basic_events[*b].beat;
+            a.total_cmp(&b)
+        });
+
+        // The beat of the next event with the same type, which ends flashes, fades and laser rotations early.
+        let mut next_beats: Vec<Option<f32>> = vec![None; self.basic_events.len()];
+        let mut following: HashMap<i32, f32> = HashMap::new();
+        for i in order.iter().rev() {
+            let event = &self.basic_events[*i];
+            next_beats[*i] = following.insert(event.event_type, event.beat);
+        }
+
+        // Lasers keep spinning after the last speed event, until the end of the map.
+        let last_beat = get_last_beat(self);
+        let mut colors: HashMap<i32, LightColor> = HashMap::new();
+        let mut rotations: HashMap<i32, f32> = HashMap::new();
+        let mut color_boxes = Vec::new();
+        let mut rotation_boxes = Vec::new();
+        let mut converted = vec![false; self.basic_events.len()];
+
+        for i in order {
+            let event = &self.basic_events[i];
+            let max_offset = next_beats[i].map_or(f32::INFINITY, |next| next - event.beat);
+
+            if let Some(group_id) = get_group_id(&options.color_groups, event.event_type) {
+                let color = colors.entry(event.event_type).or_default();
+                let data = convert_light(event, color, max_offset);
+                if !data.is_empty() {
+                    color_boxes.push(ColorEventBox {
+                        beat: event.beat,
+                        group_id,
+                        groups: vec![ColorEventGroup {
+                            data,
+                            ..Default::default()
+                        }],
+                    });
+                    converted[i] = true;
+                }
+            } else if let Some(group_id) = get_group_id(&options.rotation_groups, event.event_type)
+            {
+                let degrees = rotations.entry(event.event_type).or_default();
+                if let Some(group) = convert_rotation(event, degrees, next_beats[i], last_beat) {
+                    rotation_boxes.push(RotationEventBox {
+                        beat: event.beat,
+                        group_id,
+                        groups: vec![group],
+                    });
+                    converted[i] = true;
+                }
+            }
+        }
+
+        splice_sorted(&mut self.color_event_boxes, color_boxes);
+        splice_sorted(&mut self.rotation_event_boxes, rotation_boxes);
+
+        if options.remove_converted {
+            let mut converted = converted.into_iter();
+            self.basic_events
+                .retain(|_| !converted.next().unwrap_or_default());
+        }
+    }
+}
+
+fn get_group_id(groups: &[(i32, i32)], event_type: i32) -> Option<i32> {
+    (groups.iter())
+        .find(|(other, _)| *other == event_type)
+        .map(|(_, group_id)| *group_id)
+}
+
+/// Returns the color event data equivalent to a light event, or nothing if the value is unknown.
+///
+/// The color is updated to the color of the event, so that off events can keep the previous color.
+fn convert_light(
+    event: &BasicEvent,
+    color: &mut LightColor,
+    max_offset: f32,
+) -> Vec<ColorEventData> {
+    let get_data = |beat_offset, transition_type, color, brightness| ColorEventData {
+        beat_offset,
+        transition_type,
+        color,
+        brightness,
+        ..Default::default()
+    };
+
+    if event.value == 0 {
+        return vec![get_data(0.0, ColorTransitionType::Instant, *color, 0.0)];
+    }
+
+    *color = match (event.value - 1).div_euclid(4) {
+        0 => LightColor::Secondary,
+        1 => LightColor::Primary,
+        2 => LightColor::White,
+        _ => return vec![],
+    };
+    let brightness = event.float;
+
+    let mut data = match event.value {
+        // On:
+        1 | 5 | 9 => vec![get_data(
+            0.0,
+            ColorTransitionType::Instant,
+            *color,
+            brightness,
+        )],
+        // Flash:
+        2 | 6 | 10 => vec![
+            get_data(
+                0.0,
+                ColorTransitionType::Instant,
+                *color,
+                brightness * FLASH_BRIGHTNESS,
+            ),
+            get_data(
+                FLASH_BEATS,
+                ColorTransitionType::Transition,
+                *color,
+                brightness,
+            ),
+        ],
+        // Fade:
+        3 | 7 | 11 => vec![
+            get_data(0.0, ColorTransitionType::Instant, *color, brightness),
+            get_data(FADE_BEATS, ColorTransitionType::Transition, *color, 0.0),
+        ],
+        // Transition:
+        _ => vec![get_data(
+            0.0,
+            ColorTransitionType::Transition,
+            *color,
+            brightness,
+        )],
+    };
+
+    // Otherwise, the end of a flash or fade would override the next event,
+    // so it is cut short at the brightness it would have reached by then.
+    if let Some(i) = data.iter().position(|data| data.beat_offset >= max_offset) {
+        if i == 0 {
+            return vec![];
+        }
+        data.truncate(i + 1);
+        if let [.., start, end] = &mut data[..] {
+            let progress = (max_offset - start.beat_offset) / (end.beat_offset - start.beat_offset);
+            end.brightness = start.brightness + (end.brightness - start.brightness) * progress;
+            end.beat_offset = max_offset;
+        }
+    }
+    data
+}
+
+/// Returns a rotation group approximating a ring spin or laser speed event,
+/// updating the current rotation of the group.
+fn convert_rotation(
+    event: &BasicEvent,
+    degrees: &mut f32,
+    next_beat: Option<f32>,
+    last_beat: f32,
+) -> Option<RotationEventGroup> {
+    let get_group = |axis, data| RotationEventGroup {
+        axis,
+        data,
+        ..Default::default()
+    };
+    // Keeps the previous rotation until the transition starts.
+    let hold = RotationEventData {
+        transition_type: TransitionType::Extend,
+        ..Default::default()
+    };
+
+    match event.event_type {
+        RING_SPIN_EVENT_TYPE => {
+            let duration = next_beat.map_or(RING_SPIN_BEATS, |next| {
+                RING_SPIN_BEATS.min(next - event.beat)
+            });
+            *degrees = (*degrees + RING_SPIN_DEGREES).rem_euclid(360.0);
+            let spin = RotationEventData {
+                beat_offset: duration,
+                easing: Easing::OutQuad,
+                degrees: *degrees,
+                direction: RotationDirection::Clockwise,
+                ..Default::default()
+            };
+            Some(get_group(EventAxis::Z, vec![hold, spin]))
+        }
+        LEFT_LASER_SPEED_EVENT_TYPE | RIGHT_LASER_SPEED_EVENT_TYPE => {
+            if event.value <= 0 {
+                *degrees = 0.0;
+                let reset = RotationEventData {
+                    easing: Easing::None,
+                    ..Default::default()
+                };
+                return Some(get_group(EventAxis::Y, vec![reset]));
+            }
+
+            let duration = (next_beat.unwrap_or(last_beat) - event.beat).max(0.0);
+            let total = event.value as f32 * LASER_DEGREES_PER_BEAT * duration;
+            let direction = match event.event_type {
+                LEFT_LASER_SPEED_EVENT_TYPE => RotationDirection::Clockwise,
+                _ => RotationDirection::CounterClockwise,
+            };
+            *degrees = match direction {
+                RotationDirection::Clockwise => *degrees + total,
+                _ => *degrees - total,
+            }
+            .rem_euclid(360.0);
+
+            let spin = RotationEventData {
+                beat_offset: duration,
+                easing: Easing::Linear,
+                degrees: *degrees,
+                direction,
+                loops: (total / 360.0).floor() as i32,
+                ..Default::default()
+            };
+            Some(get_group(EventAxis::Y, vec![hold, spin]))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::playfield::Note;
+
+    fn get_basic(beat: f32, event_type: i32, value: i32) -> BasicEvent {
+        BasicEvent {
+            beat,
+            event_type,
+            value,
+            float: 1.0,
+        }
+    }
+
+    fn get_options() -> LightConversionOptions {
+        LightConversionOptions {
+            color_groups: vec![(1, 4)],
+            rotation_groups: vec![(8, 1), (12, 2), (13, 3)],
+            ..Default::default()
+        }
+    }
+
+    fn get_data(difficulty: &Difficulty) -> Vec<(f32, LightColor, f32)> {
+        (difficulty.color_event_boxes.iter())
+            .flat_map(|event_box| {
+                event_box.groups[0].data.iter().map(|data| {
+                    (
+                        event_box.beat + data.beat_offset,
+                        data.color,
+                        data.brightness,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lights() {
+        let mut difficulty = Difficulty {
+            basic_events: vec![
+                get_basic(0.0, 1, 5),
+                get_basic(1.0, 1, 3),
+                get_basic(4.0, 1, 0),
+                get_basic(5.0, 1, 11),
+                get_basic(5.5, 1, 1),
+                get_basic(6.0, 5, 1),
+            ],
+            ..Default::default()
+        };
+        difficulty.convert_basic_lighting(&get_options());
+
+        assert_eq!(
+            get_data(&difficulty),
+            vec![
+                (0.0, LightColor::Primary, 1.0),
+                (1.0, LightColor::Secondary, 1.0),
+                (2.0, LightColor::Secondary, 0.0),
+                (4.0, LightColor::Secondary, 0.0),
+                // The fade is cut short by the next event.
+                (5.0, LightColor::White, 1.0),
+                (5.5, LightColor::White, 0.5),
+                (5.5, LightColor::Secondary, 1.0),
+            ]
+        );
+        assert!(
+            difficulty
+                .color_event_boxes
+                .iter()
+                .all(|event_box| event_box.group_id == 4)
+        );
+        assert_eq!(difficulty.basic_events, vec![get_basic(6.0, 5, 1)]);
+    }
+
+    #[test]
+    fn no_groups() {
+        let basic_events = vec![get_basic(0.0, 1, 1)];
+        let mut difficulty = Difficulty {
+            basic_events: basic_events.clone(),
+            ..Default::default()
+        };
+        difficulty.convert_basic_lighting(&LightConversionOptions::default());
+
+        assert!(difficulty.color_event_boxes.is_empty());
+        assert_eq!(difficulty.basic_events, basic_events);
+    }
+
+    #[test]
+    fn rings_and_lasers() {
+        let mut difficulty = Difficulty {
+            basic_events: vec![
+                get_basic(0.0, 8, 0),
+                get_basic(0.0, 12, 3),
+                get_basic(8.0, 12, 0),
+                get_basic(8.0, 9, 0),
+            ],
+            ..Default::default()
+        };
+        difficulty.convert_basic_lighting(&get_options());
+
+        let boxes = &difficulty.rotation_event_boxes;
+        assert_eq!(boxes.len(), 3);
+
+        let ring = &boxes[0].groups[0];
+        assert_eq!(ring.axis, EventAxis::Z);
+        assert_eq!(ring.data[1].degrees, RING_SPIN_DEGREES);
+
+        // Three units of speed for eight beats is 720 degrees.
+        let laser = &boxes[1].groups[0];
+        assert_eq!(laser.data[1].beat_offset, 8.0);
+        assert_eq!((laser.data[1].degrees, laser.data[1].loops), (0.0, 2));
+        assert_eq!(boxes[2].groups[0].data[0].easing, Easing::None);
+
+        // Ring zooms are left unchanged.
+        assert_eq!(difficulty.basic_events, vec![get_basic(8.0, 9, 0)]);
+    }
+
+    #[test]
+    fn last_laser_speed() {
+        let mut difficulty = Difficulty {
+            notes: vec![Note {
+                beat: 6.0,
+                ..Default::default()
+            }],
+            basic_events: vec![get_basic(0.0, 13, 2)],
+            ..Default::default()
+        };
+        difficulty.convert_basic_lighting(&get_options());
+
+        // The lasers keep spinning until the end of the map.
+        let laser = &difficulty.rotation_event_boxes[0].groups[0];
+        assert_eq!(laser.data[1].beat_offset, 6.0);
+        assert_eq!(laser.data[1].loops, 1);
+        assert_eq!(laser.data[1].direction, RotationDirection::CounterClockwise);
+        assert!(difficulty.basic_events.is_empty());
+    }
+}
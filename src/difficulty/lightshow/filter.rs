@@ -1,5 +1,8 @@
 //! Controls which light IDs are affected by an event.
 
+mod random;
+
+use crate::difficulty::lightshow::filter::random::DotNetRandom;
 use crate::loose_bool::LooseBool;
 use loose_enum::loose_enum;
 use serde::{Deserialize, Serialize};
@@ -34,9 +37,13 @@ pub struct Filter {
     #[serde(rename = "c")]
     pub chunks: Option<i32>,
     /// > Only present in difficulty file V3.1 or higher.
+    ///
+    /// Determines whether the filtered lights are shuffled before the limit and distribution are applied.
     #[serde(rename = "n")]
     pub random_behaviour: Option<RandomBehaviour>,
     /// > Only present in difficulty file V3.1 or higher.
+    ///
+    /// The seed used to shuffle the filtered lights. Filters with the same seed will be shuffled the same way.
     #[serde(rename = "s")]
     pub random_seed: Option<i32>,
     /// > Only present in difficulty file V3.1 or higher.
@@ -78,10 +85,16 @@ impl Filter {
     /// Will panic if the light ID is greater than or equal to the group size.
     #[must_use]
     #[inline]
-    #[deprecated(note = "Experimental. Does not consider random in calculations.")]
+    #[deprecated(note = "Experimental.")]
     pub fn is_in_filter(&self, mut light_id: i32, mut group_size: i32) -> bool {
         assert!(light_id < group_size);
 
+        if self.is_shuffled() {
+            return self
+                .build_shuffled_chunks(group_size)
+                .is_in_filter(light_id);
+        }
+
         if let Some(limit) = self.limit_percent
             && limit > 0.0
             && light_id >= (group_size as f32 * limit) as i32
@@ -124,7 +137,7 @@ impl Filter {
     /// If the [`FilterType`] is `Undefined` then the result will be the same as `group_size`.
     #[must_use]
     #[inline]
-    #[deprecated(note = "Experimental. Does not consider random in calculations.")]
+    #[deprecated(note = "Experimental.")]
     pub(crate) fn count_filtered_without_limit(&self, mut group_size: i32) -> i32 {
         if let Some(chunks) = self.chunks
             && chunks > 0
@@ -154,7 +167,7 @@ impl Filter {
     /// If the [`FilterType`] is `Undefined` then the result will be the same as `group_size`.
     #[must_use]
     #[inline]
-    #[deprecated(note = "Experimental. Does not consider random in calculations.")]
+    #[deprecated(note = "Experimental.")]
     #[allow(deprecated)]
     pub fn count_filtered(&self, group_size: i32) -> i32 {
         let filtered = self.count_filtered_without_limit(group_size);
@@ -177,10 +190,16 @@ impl Filter {
     // Todo what is the behaviour when the light ID is not in the filter?
     #[must_use]
     #[inline]
-    #[deprecated(note = "Experimental. Does not consider random in calculations.")]
+    #[deprecated(note = "Experimental.")]
     pub fn get_relative_index(&self, mut light_id: i32, mut group_size: i32) -> i32 {
         assert!(light_id < group_size);
 
+        if self.is_shuffled() {
+            return self
+                .build_shuffled_chunks(group_size)
+                .get_relative_index(light_id);
+        }
+
         if self.reverse.is_true() {
             light_id = group_size - light_id;
         }
@@ -205,6 +224,163 @@ impl Filter {
             FilterType::Undefined(_) => group_size,
         }
     }
+
+    /// Returns the light chunk IDs effected by the filter, in the order that distribution is applied.
+    ///
+    /// Unlike the other filter methods, this always follows the [`RandomBehaviour`],
+    /// shuffling the chunks the same way the game does for a given [seed](Self::random_seed).
+    /// Chunks that are excluded by [the limit](Self::limit_percent) are not included.
+    /// # Undefined
+    /// If the [`FilterType`] is `Undefined` then every chunk will be included.
+    #[must_use]
+    #[deprecated(note = "Experimental.")]
+    pub fn get_chunk_order(&self, group_size: i32) -> Vec<i32> {
+        let mut order = self.get_shuffled_chunk_order(group_size);
+        order.truncate(self.apply_limit(order.len()));
+        order
+    }
+
+    /// Returns the light chunks effected by the filter, shuffled by the [`RandomBehaviour`].
+    ///
+    /// When the filter is shuffled, [`is_in_filter`](Self::is_in_filter) and
+    /// [`get_relative_index`](Self::get_relative_index) shuffle the chunks again for each light,
+    /// so this should be created once per group and reused for every light instead.
+    #[must_use]
+    #[deprecated(note = "Experimental.")]
+    pub fn get_shuffled_chunks(&self, group_size: i32) -> ShuffledChunks {
+        self.build_shuffled_chunks(group_size)
+    }
+
+    /// Returns true if the [`RandomBehaviour`] will shuffle the filtered chunks.
+    fn is_shuffled(&self) -> bool {
+        self.random_behaviour
+            .is_some_and(|behaviour| behaviour.random_elements())
+    }
+
+    /// Returns the number of elements that remain after applying the limit.
+    fn apply_limit(&self, count: usize) -> usize {
+        match self.limit_percent {
+            Some(limit) if limit > 0.0 => ((count as f32 * limit) as usize).min(count),
+            _ => count,
+        }
+    }
+
+    fn build_shuffled_chunks(&self, group_size: i32) -> ShuffledChunks {
+        let order = self.get_shuffled_chunk_order(group_size);
+        let mut positions = vec![None; group_size.max(0) as usize];
+        for (position, id) in order.iter().enumerate() {
+            positions[*id as usize] = Some(position);
+        }
+
+        ShuffledChunks {
+            group_size,
+            chunk_count: match self.chunks {
+                Some(chunks) if chunks > 0 && chunks < group_size => chunks,
+                _ => group_size,
+            },
+            limited: self.apply_limit(order.len()),
+            order,
+            positions,
+        }
+    }
+
+    /// Returns every filtered chunk ID, in distribution order, before the limit is applied.
+    ///
+    /// If [`RandomBehaviour::keep_order`] is enabled, the chunks that remain after the limit are put back into order.
+    fn get_shuffled_chunk_order(&self, group_size: i32) -> Vec<i32> {
+        let chunk_count = match self.chunks {
+            Some(chunks) if chunks > 0 && chunks < group_size => chunks,
+            _ => group_size,
+        };
+
+        let mut order: Vec<i32> = match self.filter_type {
+            FilterType::Division => {
+                let start = self.parameter2 * chunk_count / self.parameter1.max(1);
+                let end = (self.parameter2 + 1) * chunk_count / self.parameter1.max(1);
+                (start..end.max(start + 1)).collect()
+            }
+            FilterType::StepAndOffset => (self.parameter1.max(0)..chunk_count)
+                .step_by(self.parameter2.max(1) as usize)
+                .collect(),
+            FilterType::Undefined(_) => (0..chunk_count).collect(),
+        };
+        order.retain(|id| (0..chunk_count).contains(id));
+
+        if self.reverse.is_true() {
+            order.iter_mut().for_each(|id| *id = chunk_count - *id - 1);
+        }
+
+        let behaviour = self.random_behaviour.unwrap_or_default();
+
+        if behaviour.random_elements() {
+            let mut random = DotNetRandom::new(self.random_seed.unwrap_or_default());
+            let len = order.len() as i32;
+
+            for i in 0..len {
+                let j = random.next_range(i, len);
+                order.swap(i as usize, j as usize);
+            }
+
+            if behaviour.keep_order() {
+                let limited = self.apply_limit(order.len());
+
+                if self.reverse.is_true() {
+                    order[..limited].sort_unstable_by(|a, b| b.cmp(a));
+                } else {
+                    order[..limited].sort_unstable();
+                }
+            }
+        }
+
+        order
+    }
+}
+
+/// The light chunks effected by a [`Filter`], in the order that distribution is applied.
+/// Created using [`Filter::get_shuffled_chunks`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct ShuffledChunks {
+    group_size: i32,
+    chunk_count: i32,
+    /// Every filtered chunk ID, in distribution order, before the limit is applied.
+    order: Vec<i32>,
+    /// The number of chunks that remain after the limit is applied.
+    limited: usize,
+    /// The position of each chunk ID in the order, if it is filtered.
+    positions: Vec<Option<usize>>,
+}
+
+impl ShuffledChunks {
+    /// Returns the filtered chunk IDs in distribution order, excluding those removed by [the limit](Filter::limit_percent).
+    pub fn get_order(&self) -> &[i32] {
+        &self.order[..self.limited]
+    }
+
+    /// Returns true if the light ID is in the filter, the same as [`Filter::is_in_filter`].
+    pub fn is_in_filter(&self, light_id: i32) -> bool {
+        self.get_position(light_id)
+            .is_some_and(|position| position < self.limited)
+    }
+
+    /// Returns the light's position in the distribution order, the same as [`Filter::get_relative_index`].
+    pub fn get_relative_index(&self, light_id: i32) -> i32 {
+        self.get_position(light_id).unwrap_or(self.order.len()) as i32
+    }
+
+    fn get_position(&self, light_id: i32) -> Option<usize> {
+        let chunk_id = if self.chunk_count < self.group_size {
+            (light_id as f32 / (self.group_size as f32 / self.chunk_count as f32)) as i32
+        } else {
+            light_id
+        };
+        let index = usize::try_from(chunk_id).ok()?;
+        self.positions.get(index).copied().flatten()
+    }
 }
 
 loose_enum! {
@@ -231,6 +407,9 @@ loose_enum! {
 }
 
 loose_enum!(
+    /// Controls whether the filtered lights are shuffled, using the filter's [seed](Filter::random_seed).
+    ///
+    /// The value is a set of bit flags, so a value of 3 (`Undefined(3)`) enables both behaviours.
     #[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Hash)]
     #[cfg_attr(
         feature = "bevy_reflect",
@@ -240,11 +419,27 @@ loose_enum!(
     pub enum RandomBehaviour: i32 {
         #[default]
         None = 0,
+        /// After shuffling and applying the limit, the remaining lights are put back into their original order.
+        ///
+        /// Has no effect unless [`RandomElements`](Self::RandomElements) is also enabled.
         KeepOrder = 1,
+        /// The filtered lights are shuffled before the limit and distribution are applied.
         RandomElements = 2,
     }
 );
 
+impl RandomBehaviour {
+    /// Returns true if the keep order flag is set, that is either `KeepOrder` or `Undefined(3)`.
+    pub fn keep_order(&self) -> bool {
+        i32::from(*self) & 1 != 0
+    }
+
+    /// Returns true if the random elements flag is set, that is either `RandomElements` or `Undefined(3)`.
+    pub fn random_elements(&self) -> bool {
+        i32::from(*self) & 2 != 0
+    }
+}
+
 loose_enum!(
     /// Controls whether to extend wave distributions so they match the duration before the limit was applied.
     ///
@@ -571,4 +766,136 @@ mod tests {
         assert_eq!(filter.count_filtered_without_limit(12), 12);
         assert!((0..12).all(|i| filter.get_relative_index(i, 12) == i));
     }
+
+    #[test]
+    fn random_is_permutation() {
+        let filter = Filter {
+            random_behaviour: Some(RandomBehaviour::RandomElements),
+            random_seed: Some(42),
+            ..Default::default()
+        };
+
+        let mut order = filter.get_chunk_order(12);
+        assert_ne!(order, (0..12).collect::<Vec<_>>());
+
+        order.sort();
+        assert_eq!(order, (0..12).collect::<Vec<_>>());
+        assert!((0..12).all(|i| filter.is_in_filter(i, 12)));
+    }
+
+    #[test]
+    fn random_same_seed() {
+        let filter = Filter {
+            random_behaviour: Some(RandomBehaviour::RandomElements),
+            random_seed: Some(42),
+            ..Default::default()
+        };
+        let other_seed = Filter {
+            random_seed: Some(43),
+            ..filter.clone()
+        };
+
+        assert_eq!(
+            filter.get_chunk_order(12),
+            filter.clone().get_chunk_order(12)
+        );
+        assert_ne!(filter.get_chunk_order(12), other_seed.get_chunk_order(12));
+    }
+
+    #[test]
+    fn random_relative_index_matches_order() {
+        let filter = Filter {
+            random_behaviour: Some(RandomBehaviour::RandomElements),
+            random_seed: Some(7),
+            ..Default::default()
+        };
+
+        for (index, id) in filter.get_chunk_order(12).into_iter().enumerate() {
+            assert_eq!(filter.get_relative_index(id, 12), index as i32);
+        }
+    }
+
+    #[test]
+    fn shuffled_chunks_match_filter() {
+        let filter = Filter {
+            random_behaviour: Some(RandomBehaviour::RandomElements),
+            random_seed: Some(7),
+            chunks: Some(4),
+            limit_percent: Some(0.5),
+            ..Default::default()
+        };
+
+        let shuffled = filter.get_shuffled_chunks(12);
+        assert_eq!(shuffled.get_order(), filter.get_chunk_order(12));
+        for i in 0..12 {
+            assert_eq!(shuffled.is_in_filter(i), filter.is_in_filter(i, 12));
+            assert_eq!(
+                shuffled.get_relative_index(i),
+                filter.get_relative_index(i, 12)
+            );
+        }
+    }
+
+    #[test]
+    fn random_with_limit() {
+        let filter = Filter {
+            random_behaviour: Some(RandomBehaviour::RandomElements),
+            random_seed: Some(3),
+            limit_percent: Some(0.5),
+            ..Default::default()
+        };
+
+        let order = filter.get_chunk_order(12);
+        assert_eq!(order.len(), 6);
+        assert_eq!(filter.count_filtered(12), 6);
+        assert!((0..12).all(|i| filter.is_in_filter(i, 12) == order.contains(&i)));
+    }
+
+    #[test]
+    fn random_keep_order() {
+        let shuffled = Filter {
+            random_behaviour: Some(RandomBehaviour::RandomElements),
+            random_seed: Some(3),
+            limit_percent: Some(0.5),
+            ..Default::default()
+        };
+        let ordered = Filter {
+            random_behaviour: Some(RandomBehaviour::Undefined(3)),
+            ..shuffled.clone()
+        };
+
+        let mut expected = shuffled.get_chunk_order(12);
+        expected.sort();
+
+        assert_eq!(ordered.get_chunk_order(12), expected);
+        assert!((0..12).all(|i| ordered.is_in_filter(i, 12) == shuffled.is_in_filter(i, 12)));
+    }
+
+    #[test]
+    fn keep_order_without_random_elements() {
+        let filter = Filter {
+            random_behaviour: Some(RandomBehaviour::KeepOrder),
+            random_seed: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(filter.get_chunk_order(12), (0..12).collect::<Vec<_>>());
+        assert!((0..12).all(|i| filter.get_relative_index(i, 12) == i));
+    }
+
+    #[test]
+    fn random_with_chunks() {
+        let filter = Filter {
+            chunks: Some(6),
+            random_behaviour: Some(RandomBehaviour::RandomElements),
+            random_seed: Some(11),
+            ..Default::default()
+        };
+
+        let order = filter.get_chunk_order(12);
+        assert_eq!(order.len(), 6);
+        assert!((0..6).all(|i| {
+            filter.get_relative_index(i * 2, 12) == filter.get_relative_index(i * 2 + 1, 12)
+        }));
+    }
 }
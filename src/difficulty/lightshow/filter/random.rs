@@ -0,0 +1,136 @@
+//! A port of the seeded `System.Random` generator used by the game.
+//!
+//! The game shuffles filtered lights using .NET's seeded random number generator,
+//! so this needs to produce the exact same sequence for a given seed.
+
+const MBIG: i32 = i32::MAX;
+const MSEED: i32 = 161803398;
+
+/// Knuth's subtractive random number generator, as implemented by .NET's `System.Random(int seed)`.
+#[derive(Debug, Clone)]
+pub(crate) struct DotNetRandom {
+    seed_array: [i32; 56],
+    inext: usize,
+    inextp: usize,
+}
+
+impl DotNetRandom {
+    pub(crate) fn new(seed: i32) -> Self {
+        let subtraction = if seed == i32::MIN {
+            i32::MAX
+        } else {
+            seed.abs()
+        };
+
+        let mut seed_array = [0; 56];
+        let mut mj = MSEED.wrapping_sub(subtraction);
+        seed_array[55] = mj;
+        let mut mk = 1i32;
+
+        for i in 1..55 {
+            let ii = (21 * i) % 55;
+            seed_array[ii] = mk;
+            mk = mj.wrapping_sub(mk);
+            if mk < 0 {
+                mk = mk.wrapping_add(MBIG);
+            }
+            mj = seed_array[ii];
+        }
+
+        for _ in 1..5 {
+            for i in 1..56 {
+                seed_array[i] = seed_array[i].wrapping_sub(seed_array[1 + (i + 30) % 55]);
+                if seed_array[i] < 0 {
+                    seed_array[i] = seed_array[i].wrapping_add(MBIG);
+                }
+            }
+        }
+
+        Self {
+            seed_array,
+            inext: 0,
+            inextp: 21,
+        }
+    }
+
+    fn internal_sample(&mut self) -> i32 {
+        let mut inext = self.inext + 1;
+        if inext >= 56 {
+            inext = 1;
+        }
+
+        let mut inextp = self.inextp + 1;
+        if inextp >= 56 {
+            inextp = 1;
+        }
+
+        let mut value = self.seed_array[inext].wrapping_sub(self.seed_array[inextp]);
+        if value == MBIG {
+            value -= 1;
+        }
+        if value < 0 {
+            value = value.wrapping_add(MBIG);
+        }
+
+        self.seed_array[inext] = value;
+        self.inext = inext;
+        self.inextp = inextp;
+
+        value
+    }
+
+    /// Equivalent to `Random.NextDouble()`, returning a value in the range `0.0..1.0`.
+    pub(crate) fn next_double(&mut self) -> f64 {
+        self.internal_sample() as f64 * (1.0 / MBIG as f64)
+    }
+
+    /// Equivalent to `Random.Next(min, max)`, returning a value in the range `min..max`.
+    pub(crate) fn next_range(&mut self, min: i32, max: i32) -> i32 {
+        let range = max as i64 - min as i64;
+        (self.next_double() * range as f64) as i64 as i32 + min
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_dotnet_seed_zero() {
+        let mut random = DotNetRandom::new(0);
+
+        assert_eq!(random.internal_sample(), 1559595546);
+        assert_eq!(random.internal_sample(), 1755192844);
+        assert_eq!(random.internal_sample(), 1649316166);
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = DotNetRandom::new(1234);
+        let mut b = DotNetRandom::new(1234);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_range(0, 12), b.next_range(0, 12));
+        }
+    }
+
+    #[test]
+    fn negative_seed_matches_positive() {
+        let mut a = DotNetRandom::new(-42);
+        let mut b = DotNetRandom::new(42);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_double(), b.next_double());
+        }
+    }
+
+    #[test]
+    fn range_is_respected() {
+        let mut random = DotNetRandom::new(7);
+
+        for i in 0..100 {
+            let value = random.next_range(i, 100);
+            assert!((i..100).contains(&value));
+        }
+    }
+}
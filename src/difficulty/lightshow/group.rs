@@ -22,6 +22,8 @@ pub trait EventBox: Timed {
     type Group: EventGroup<Data = Self::Data>;
     type Data: EventData;
 
+    /// Returns the ID of the collection of objects that this event effects.
+    fn get_group_id(&self) -> i32;
     fn get_groups(&self) -> &Vec<Self::Group>;
 }
 
@@ -33,6 +35,10 @@ macro_rules! impl_event_box {
             type Group = $group;
             type Data = $data;
 
+            fn get_group_id(&self) -> i32 {
+                self.group_id
+            }
+
             fn get_groups(&self) -> &Vec<Self::Group> {
                 &self.groups
             }
@@ -50,17 +56,17 @@ pub trait EventGroup {
     /// Returns the number of beats that the event will be offset for a given light ID.
     /// # Panics
     /// Will panic if the light ID is greater than or equal to the group size.
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     fn get_beat_offset(&self, light_id: i32, group_size: i32) -> f32;
 
     /// Returns the value (i.e. brightness) that the event will be offset for a given light ID.
     /// # Panics
     /// Will panic if the light ID is greater than or equal to the group size.
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     fn get_value_offset(&self, light_id: i32, group_size: i32) -> f32;
 
     /// Returns the duration of the group in beats.
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     fn get_duration(&self, group_size: i32) -> f32;
 }
 
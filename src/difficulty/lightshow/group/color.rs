@@ -95,7 +95,7 @@ impl ColorEventGroup {
     /// Returns the brightness that the event will be offset for a given light ID.
     /// # Panics
     /// Will panic if the light ID is greater than or equal to the group size.
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     #[allow(deprecated)]
     pub fn get_brightness_offset(&self, light_id: i32, group_size: i32) -> f32 {
         self.bright_dist_type.compute_value_offset(
@@ -160,6 +160,52 @@ impl Default for ColorEventData {
 
 impl_event_data!(ColorEventData);
 
+impl ColorEventData {
+    /// Returns true if the event will strobe, meaning it has a positive [frequency](Self::strobe_frequency)
+    /// and the "off" state differs from the event's [brightness](Self::brightness).
+    pub fn is_strobing(&self) -> bool {
+        self.strobe_frequency > 0 && self.get_strobe_off_brightness() != self.brightness
+    }
+
+    /// Returns the brightness of the "off" strobe state.
+    ///
+    /// Before difficulty file V3.3, strobes always turned the light off, so a missing
+    /// [strobe brightness](Self::strobe_brightness) will result in zero.
+    pub fn get_strobe_off_brightness(&self) -> f32 {
+        self.strobe_brightness.unwrap_or(0.0)
+    }
+
+    /// Returns the brightness of the event at a number of beats after it takes place, including strobing.
+    ///
+    /// Each strobe starts in the "on" state ([brightness](Self::brightness)) and switches to the "off" state
+    /// ([strobe brightness](Self::get_strobe_off_brightness)) halfway through.
+    /// If [strobe fade](Self::strobe_fade) is enabled, the brightness instead fades linearly between the two states.
+    ///
+    /// This does not consider transitions from or to other events.
+    pub fn get_brightness(&self, beats_since_event: f32) -> f32 {
+        if self.strobe_frequency <= 0 || beats_since_event < 0.0 {
+            return self.brightness;
+        }
+
+        let on = self.brightness;
+        let off = self.get_strobe_off_brightness();
+        let phase = (beats_since_event * self.strobe_frequency as f32).rem_euclid(1.0);
+
+        if self.strobe_fade.is_some_and(|fade| fade.is_true()) {
+            let fraction = if phase < 0.5 {
+                phase * 2.0
+            } else {
+                (1.0 - phase) * 2.0
+            };
+            on + (off - on) * fraction
+        } else if phase < 0.5 {
+            on
+        } else {
+            off
+        }
+    }
+}
+
 loose_enum! {
     /// Controls how the state is changed relative to the previous event.
     #[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -454,4 +500,82 @@ mod tests {
 
         assert!((0..6).all(|i| group.get_brightness_offset(i, 12) == (i * 2) as f32));
     }
+
+    #[test]
+    fn no_strobe() {
+        let data = ColorEventData {
+            brightness: 0.5,
+            ..Default::default()
+        };
+
+        assert!(!data.is_strobing());
+        assert!((0..16).all(|i| data.get_brightness(i as f32 * 0.1) == 0.5));
+    }
+
+    #[test]
+    fn hard_strobe() {
+        let data = ColorEventData {
+            strobe_frequency: 2,
+            ..Default::default()
+        };
+
+        assert!(data.is_strobing());
+        assert_eq!(data.get_brightness(0.0), 1.0);
+        assert_eq!(data.get_brightness(0.2), 1.0);
+        assert_eq!(data.get_brightness(0.25), 0.0);
+        assert_eq!(data.get_brightness(0.4), 0.0);
+        assert_eq!(data.get_brightness(0.5), 1.0);
+    }
+
+    #[test]
+    fn faded_strobe() {
+        let data = ColorEventData {
+            strobe_frequency: 1,
+            strobe_fade: Some(LooseBool::True),
+            ..Default::default()
+        };
+
+        assert_eq!(data.get_brightness(0.0), 1.0);
+        assert_eq!(data.get_brightness(0.25), 0.5);
+        assert_eq!(data.get_brightness(0.5), 0.0);
+        assert_eq!(data.get_brightness(0.75), 0.5);
+        assert_eq!(data.get_brightness(1.0), 1.0);
+    }
+
+    #[test]
+    fn strobe_brightness() {
+        let data = ColorEventData {
+            brightness: 1.0,
+            strobe_frequency: 1,
+            strobe_brightness: Some(0.5),
+            ..Default::default()
+        };
+
+        assert_eq!(data.get_brightness(0.0), 1.0);
+        assert_eq!(data.get_brightness(0.5), 0.5);
+    }
+
+    #[test]
+    fn strobe_brightness_missing() {
+        let data = ColorEventData {
+            strobe_frequency: 1,
+            strobe_brightness: None,
+            strobe_fade: None,
+            ..Default::default()
+        };
+
+        assert_eq!(data.get_brightness(0.5), 0.0);
+    }
+
+    #[test]
+    fn strobe_brightness_equal() {
+        let data = ColorEventData {
+            strobe_frequency: 4,
+            strobe_brightness: Some(1.0),
+            ..Default::default()
+        };
+
+        assert!(!data.is_strobing());
+        assert!((0..16).all(|i| data.get_brightness(i as f32 * 0.1) == 1.0));
+    }
 }
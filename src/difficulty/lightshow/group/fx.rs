@@ -286,7 +286,7 @@ impl FxEventGroup {
     /// Returns the FX value that the event will be offset for a given light ID.
     /// # Panics
     /// Will panic if the light ID is greater than or equal to the group size.
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     #[allow(deprecated)]
     pub fn get_fx_offset(&self, light_id: i32, group_size: i32) -> f32 {
         self.fx_dist_type.compute_value_offset(
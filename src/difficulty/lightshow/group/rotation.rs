@@ -2,7 +2,7 @@
 
 use crate::difficulty::lightshow::easing::Easing;
 use crate::difficulty::lightshow::filter::Filter;
-use crate::difficulty::lightshow::group::EventData;
+use crate::difficulty::lightshow::group::{EventData, EventGroup};
 use crate::difficulty::lightshow::{DistributionType, EventAxis, TransitionType};
 use crate::loose_bool::LooseBool;
 use crate::{impl_event_box, impl_event_group, impl_timed};
@@ -102,7 +102,7 @@ impl RotationEventGroup {
     /// Returns the number of degrees that the event will be offset for a given light ID.
     /// # Panics
     /// Will panic if the light ID is greater than or equal to the group size.
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     #[allow(deprecated)]
     pub fn get_rotation_offset(&self, light_id: i32, group_size: i32) -> f32 {
         self.rotation_dist_type.compute_value_offset(
@@ -147,6 +147,38 @@ impl EventData for RotationEventData {
     }
 }
 
+impl RotationEventData {
+    /// Returns the absolute number of degrees that the event will rotate to, starting from a previous rotation.
+    ///
+    /// The [`RotationDirection`] decides which way the target angle is approached,
+    /// with clockwise increasing the angle, and each [loop](Self::loops) adds a full rotation in that direction.
+    /// The `offset` is added to the event's [degrees](Self::degrees), and is usually the rotation distribution.
+    pub fn resolve_degrees(&self, previous: f32, offset: f32) -> f32 {
+        resolve_degrees(previous, self.degrees + offset, self.direction, self.loops)
+    }
+}
+
+fn resolve_degrees(previous: f32, target: f32, direction: RotationDirection, loops: i32) -> f32 {
+    let from = previous.rem_euclid(360.0);
+    let to = target.rem_euclid(360.0);
+    let loops = loops.max(0) as f32 * 360.0;
+
+    let delta = match direction {
+        RotationDirection::Clockwise => (to - from).rem_euclid(360.0) + loops,
+        RotationDirection::CounterClockwise => -(from - to).rem_euclid(360.0) - loops,
+        RotationDirection::Automatic | RotationDirection::Undefined(_) => {
+            let delta = (to - from + 180.0).rem_euclid(360.0) - 180.0;
+            if delta < 0.0 {
+                delta - loops
+            } else {
+                delta + loops
+            }
+        }
+    };
+
+    previous + delta
+}
+
 loose_enum! {
     /// Determines the direction that the rotation event will rotate.
     /// Automatic will choose the shortest distance.
@@ -163,3 +195,310 @@ loose_enum! {
         CounterClockwise = 2,
     }
 }
+
+impl RotationDirection {
+    /// Returns the direction that will rotate the opposite way, used when an axis is [inverted](RotationEventGroup::invert_axis).
+    pub fn inverted(&self) -> Self {
+        match self {
+            RotationDirection::Clockwise => RotationDirection::CounterClockwise,
+            RotationDirection::CounterClockwise => RotationDirection::Clockwise,
+            other => *other,
+        }
+    }
+}
+
+/// The rotation of a single light around a single axis over time, resolved from a list of [`RotationEventBox`]s.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct RotationTimeline {
+    /// The resolved keyframes, sorted by beat.
+    pub keyframes: Vec<RotationKeyframe>,
+}
+
+/// A resolved [`RotationEventData`] for a single light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct RotationKeyframe {
+    /// The absolute beat that the rotation is reached, including beat distribution.
+    pub beat: f32,
+    /// The absolute number of degrees, including distribution, [direction](RotationDirection), loops, and axis inversion.
+    ///
+    /// This is not wrapped, so a value of 720 means the light has done two full rotations.
+    pub degrees: f32,
+    /// The easing used to transition from the previous keyframe.
+    ///
+    /// This will be `None` if the rotation is reached instantly.
+    pub easing: Option<Easing>,
+}
+
+impl RotationTimeline {
+    /// Resolves the rotation of a light around an axis.
+    ///
+    /// Only boxes matching the `group_id` and groups matching the `axis` and containing the light are used.
+    /// Every light starts with a rotation of zero degrees.
+    /// # Panics
+    /// Will panic if the light ID is greater than or equal to the group size.
+    #[deprecated(note = "Experimental.")]
+    #[allow(deprecated)]
+    pub fn new(
+        boxes: &[RotationEventBox],
+        group_id: i32,
+        light_id: i32,
+        group_size: i32,
+        axis: EventAxis,
+    ) -> Self {
+        let mut events = Vec::new();
+
+        for event_box in boxes
+            .iter()
+            .filter(|event_box| event_box.group_id == group_id)
+        {
+            for group in &event_box.groups {
+                if group.axis != axis || !group.filter.is_in_filter(light_id, group_size) {
+                    continue;
+                }
+
+                let beat = event_box.beat + group.get_beat_offset(light_id, group_size);
+                let offset = group.get_rotation_offset(light_id, group_size);
+
+                for (i, data) in group.data.iter().enumerate() {
+                    let offset = if i > 0 || group.rotation_dist_effect_first.is_true() {
+                        offset
+                    } else {
+                        0.0
+                    };
+
+                    events.push((beat + data.beat_offset, data, offset, group.invert_axis));
+                }
+            }
+        }
+
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut keyframes: Vec<RotationKeyframe> = Vec::with_capacity(events.len());
+        let mut previous = 0.0;
+
+        for (beat, data, offset, invert_axis) in events {
+            if let TransitionType::Extend = data.transition_type {
+                keyframes.push(RotationKeyframe {
+                    beat,
+                    degrees: previous,
+                    easing: None,
+                });
+                continue;
+            }
+
+            let (target, direction) = if invert_axis.is_true() {
+                (-(data.degrees + offset), data.direction.inverted())
+            } else {
+                (data.degrees + offset, data.direction)
+            };
+
+            previous = resolve_degrees(previous, target, direction, data.loops);
+
+            keyframes.push(RotationKeyframe {
+                beat,
+                degrees: previous,
+                easing: match data.easing {
+                    Easing::None => None,
+                    easing => Some(easing),
+                },
+            });
+        }
+
+        Self { keyframes }
+    }
+
+    /// Returns the number of degrees the light is rotated at a beat.
+    ///
+    /// Between keyframes, the rotation is eased towards the next keyframe.
+    pub fn get_degrees(&self, beat: f32) -> f32 {
+        let index = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.beat <= beat);
+
+        let Some(current) = index.checked_sub(1).map(|i| self.keyframes[i]) else {
+            return 0.0;
+        };
+
+        let Some(next) = self.keyframes.get(index) else {
+            return current.degrees;
+        };
+
+        let Some(easing) = next.easing else {
+            return current.degrees;
+        };
+
+        let duration = next.beat - current.beat;
+        if duration <= 0.0 {
+            return current.degrees;
+        }
+
+        let progress = easing.ease((beat - current.beat) / duration);
+        current.degrees + (next.degrees - current.degrees) * progress
+    }
+}
+
+#[allow(deprecated)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_box(beat: f32, data: RotationEventData) -> RotationEventBox {
+        RotationEventBox {
+            beat,
+            group_id: 0,
+            groups: vec![RotationEventGroup {
+                data: vec![data],
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn get_data(degrees: f32, direction: RotationDirection, loops: i32) -> RotationEventData {
+        RotationEventData {
+            transition_type: TransitionType::Transition,
+            easing: Easing::Linear,
+            degrees,
+            direction,
+            loops,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn automatic_takes_shortest_path() {
+        let data = get_data(350.0, RotationDirection::Automatic, 0);
+
+        assert_eq!(data.resolve_degrees(10.0, 0.0), -10.0);
+        assert_eq!(data.resolve_degrees(370.0, 0.0), 350.0);
+    }
+
+    #[test]
+    fn clockwise_increases() {
+        let data = get_data(90.0, RotationDirection::Clockwise, 0);
+
+        assert_eq!(data.resolve_degrees(180.0, 0.0), 450.0);
+        assert_eq!(data.resolve_degrees(0.0, 0.0), 90.0);
+    }
+
+    #[test]
+    fn counter_clockwise_decreases() {
+        let data = get_data(90.0, RotationDirection::CounterClockwise, 0);
+
+        assert_eq!(data.resolve_degrees(0.0, 0.0), -270.0);
+        assert_eq!(data.resolve_degrees(180.0, 0.0), 90.0);
+    }
+
+    #[test]
+    fn loops_add_full_rotations() {
+        assert_eq!(
+            get_data(90.0, RotationDirection::Clockwise, 2).resolve_degrees(0.0, 0.0),
+            810.0
+        );
+        assert_eq!(
+            get_data(90.0, RotationDirection::CounterClockwise, 1).resolve_degrees(0.0, 0.0),
+            -630.0
+        );
+    }
+
+    #[test]
+    fn timeline_transitions() {
+        let boxes = vec![
+            get_box(0.0, get_data(0.0, RotationDirection::Automatic, 0)),
+            get_box(4.0, get_data(90.0, RotationDirection::Automatic, 0)),
+        ];
+        let timeline = RotationTimeline::new(&boxes, 0, 0, 1, EventAxis::X);
+
+        assert_eq!(timeline.get_degrees(-1.0), 0.0);
+        assert_eq!(timeline.get_degrees(2.0), 45.0);
+        assert_eq!(timeline.get_degrees(4.0), 90.0);
+        assert_eq!(timeline.get_degrees(10.0), 90.0);
+    }
+
+    #[test]
+    fn timeline_extend_holds_previous() {
+        let boxes = vec![
+            get_box(0.0, get_data(45.0, RotationDirection::Automatic, 0)),
+            get_box(
+                4.0,
+                RotationEventData {
+                    transition_type: TransitionType::Extend,
+                    ..get_data(180.0, RotationDirection::Automatic, 0)
+                },
+            ),
+        ];
+        let timeline = RotationTimeline::new(&boxes, 0, 0, 1, EventAxis::X);
+
+        assert_eq!(timeline.get_degrees(2.0), 45.0);
+        assert_eq!(timeline.get_degrees(5.0), 45.0);
+    }
+
+    #[test]
+    fn timeline_no_easing_is_instant() {
+        let boxes = vec![
+            get_box(0.0, get_data(0.0, RotationDirection::Automatic, 0)),
+            get_box(
+                4.0,
+                RotationEventData {
+                    easing: Easing::None,
+                    ..get_data(90.0, RotationDirection::Automatic, 0)
+                },
+            ),
+        ];
+        let timeline = RotationTimeline::new(&boxes, 0, 0, 1, EventAxis::X);
+
+        assert_eq!(timeline.get_degrees(3.9), 0.0);
+        assert_eq!(timeline.get_degrees(4.0), 90.0);
+    }
+
+    #[test]
+    fn timeline_inverted_axis() {
+        let mut event_box = get_box(0.0, get_data(90.0, RotationDirection::Clockwise, 0));
+        event_box.groups[0].invert_axis = LooseBool::True;
+        let timeline = RotationTimeline::new(&[event_box], 0, 0, 1, EventAxis::X);
+
+        assert_eq!(timeline.get_degrees(0.0), -90.0);
+    }
+
+    #[test]
+    fn timeline_ignores_other_axes_and_groups() {
+        let mut other_axis = get_box(0.0, get_data(90.0, RotationDirection::Automatic, 0));
+        other_axis.groups[0].axis = EventAxis::Y;
+        let mut other_group = get_box(0.0, get_data(90.0, RotationDirection::Automatic, 0));
+        other_group.group_id = 1;
+
+        let timeline = RotationTimeline::new(&[other_axis, other_group], 0, 0, 1, EventAxis::X);
+
+        assert!(timeline.keyframes.is_empty());
+        assert_eq!(timeline.get_degrees(1.0), 0.0);
+    }
+
+    #[test]
+    fn timeline_rotation_distribution() {
+        let event_box = RotationEventBox {
+            beat: 0.0,
+            group_id: 0,
+            groups: vec![RotationEventGroup {
+                rotation_dist_type: DistributionType::Step,
+                rotation_dist_value: 10.0,
+                data: vec![get_data(0.0, RotationDirection::Clockwise, 0)],
+                ..Default::default()
+            }],
+        };
+
+        for i in 0..4 {
+            let timeline =
+                RotationTimeline::new(std::slice::from_ref(&event_box), 0, i, 4, EventAxis::X);
+            assert_eq!(timeline.get_degrees(0.0), i as f32 * 10.0);
+        }
+    }
+}
@@ -107,7 +107,7 @@ impl TranslationEventGroup {
     /// Returns the number of units that the event will be offset for a given light ID.
     /// # Panics
     /// Will panic if the light ID is greater than or equal to the group size.
-    #[deprecated(note = "Experimental. Does not consider random in filter calculations.")]
+    #[deprecated(note = "Experimental.")]
     #[allow(deprecated)]
     pub fn get_translation_offset(&self, light_id: i32, group_size: i32) -> f32 {
         self.translation_dist_type.compute_value_offset(
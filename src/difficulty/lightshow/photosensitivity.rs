@@ -0,0 +1,566 @@
+//! Finds sections of a lightshow that flash often enough to be a photosensitivity risk.
+//!
+//! A flash is counted each time the lights suddenly get brighter or change color:
+//! - [`BasicEvent`] light events that turn on, flash, or fade, unless the light was already on with the same color.
+//! - Instant [`ColorEventData`] that brightens its group or changes its color, as well as each cycle of a strobe.
+//! - [`ColorBoostEvent`](crate::ColorBoostEvent)s that toggle boost colors.
+//!
+//! Flashes that happen at (almost) the same time are counted once, as they are perceived as a single flash.
+//! Beat distribution is ignored, so wave effects across a group are treated as a single flash.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::bpm::BpmTimeline;
+use crate::difficulty::lightshow::basic::BasicEvent;
+use crate::difficulty::lightshow::group::color::{ColorEventData, ColorTransitionType, LightColor};
+use crate::timing_traits::{Duration, Timed};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The basic event types that control lights.
+const LIGHT_EVENT_TYPES: [i32; 9] = [0, 1, 2, 3, 4, 6, 7, 10, 11];
+/// The maximum number of strobes counted for a single event, which limits the cost of extremely high frequencies.
+const MAX_STROBES_PER_EVENT: usize = 100_000;
+
+/// Controls the thresholds used when [searching for flashes](Difficulty::find_flash_warnings).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct FlashOptions {
+    /// The number of flashes per second that is considered safe.
+    /// Sections with more flashes than this will produce a warning.
+    ///
+    /// The default is three, which is the limit defined by WCAG.
+    pub max_flashes_per_second: f32,
+    /// The number of flashes per second where a warning becomes [severe](FlashSeverity::Severe).
+    pub severe_flashes_per_second: f32,
+    /// The length of the sliding window (in seconds) that flashes are counted in.
+    pub window: f32,
+    /// The minimum change in brightness that is considered a flash, where one is normal brightness.
+    pub min_brightness_change: f32,
+    /// Flashes that are closer together than this number of seconds are counted as a single flash.
+    pub merge_threshold: f32,
+}
+
+impl Default for FlashOptions {
+    fn default() -> Self {
+        Self {
+            max_flashes_per_second: 3.0,
+            severe_flashes_per_second: 6.0,
+            window: 1.0,
+            min_brightness_change: 0.5,
+            merge_threshold: 1.0 / 60.0,
+        }
+    }
+}
+
+/// How dangerous a [`FlashWarning`] is.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum FlashSeverity {
+    /// The flash rate exceeds the [safe limit](FlashOptions::max_flashes_per_second).
+    Warning,
+    /// The flash rate exceeds the [severe limit](FlashOptions::severe_flashes_per_second).
+    Severe,
+}
+
+/// A section of a lightshow that flashes more than the safe limit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct FlashWarning {
+    /// The beat of the first flash in the section.
+    pub start_beat: f32,
+    /// The beat of the last flash in the section.
+    pub end_beat: f32,
+    /// The time of the first flash in the section, in seconds.
+    pub start_seconds: f32,
+    /// The time of the last flash in the section, in seconds.
+    pub end_seconds: f32,
+    /// The total number of flashes in the section.
+    pub flash_count: usize,
+    /// The highest number of flashes per second within the section.
+    pub peak_flashes_per_second: f32,
+    pub severity: FlashSeverity,
+}
+
+impl Difficulty {
+    /// Returns the sections of the lightshow that flash more than the [safe limit](FlashOptions::max_flashes_per_second),
+    /// sorted by time.
+    ///
+    /// The base BPM is defined by the map's [info file](crate::info::Beatmap::bpm).
+    /// For details on what counts as a flash, see the [module docs](crate::difficulty::lightshow::photosensitivity).
+    pub fn find_flash_warnings(&self, base_bpm: f32, options: &FlashOptions) -> Vec<FlashWarning> {
+        let timeline = self.get_bpm_timeline(base_bpm);
+
+        let mut flashes = get_basic_flashes(self, options);
+        flashes.extend(get_color_event_flashes(self, options));
+        flashes.extend(get_boost_flashes(self));
+
+        let mut flashes: Vec<f32> = flashes
+            .into_iter()
+            .map(|beat| timeline.get_seconds(beat))
+            .collect();
+        flashes.sort_by(f32::total_cmp);
+        flashes.dedup_by(|b, a| *b - *a < options.merge_threshold);
+
+        group_flashes(&flashes, &timeline, options)
+    }
+}
+
+/// Returns the beats of every flash caused by a [`BasicEvent`].
+fn get_basic_flashes(difficulty: &Difficulty, options: &FlashOptions) -> Vec<f32> {
+    let mut states: HashMap<i32, (bool, i32)> = HashMap::new();
+    let mut events: Vec<&BasicEvent> = difficulty
+        .basic_events
+        .iter()
+        .filter(|event| LIGHT_EVENT_TYPES.contains(&event.event_type))
+        .collect();
+    events.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+    let mut flashes = Vec::new();
+
+    for event in events {
+        let state = states.entry(event.event_type).or_insert((false, 0));
+        let color = (event.value - 1).div_euclid(4);
+
+        match event.value {
+            0 => *state = (false, color),
+            // On:
+            1 | 5 | 9 => {
+                let (was_on, previous_color) = *state;
+                if event.float >= options.min_brightness_change
+                    && (!was_on || previous_color != color)
+                {
+                    flashes.push(event.beat);
+                }
+                *state = (true, color);
+            }
+            // Flash and fade:
+            2 | 3 | 6 | 7 | 10 | 11 => {
+                if event.float >= options.min_brightness_change {
+                    flashes.push(event.beat);
+                }
+                *state = (true, color);
+            }
+            // Transitions and unknown values are gradual.
+            _ => *state = (true, color),
+        }
+    }
+
+    flashes
+}
+
+/// Returns the beats of every flash caused by a [`ColorEventData`], including strobes.
+///
+/// The lights of each group ID and [filter](crate::Filter) are tracked separately,
+/// so event groups that control different lights don't affect each other.
+fn get_color_event_flashes(difficulty: &Difficulty, options: &FlashOptions) -> Vec<f32> {
+    // The index of each group ID and filter, which is used instead of the filter as it can't be hashed.
+    let mut light_ids: HashMap<(i32, String), usize> = HashMap::new();
+    let mut events: Vec<(usize, f32, &ColorEventData)> = Vec::new();
+
+    for event_box in &difficulty.color_event_boxes {
+        for group in &event_box.groups {
+            let filter = serde_json::to_string(&group.filter).unwrap_or_default();
+            let next_id = light_ids.len();
+            let lights = *light_ids
+                .entry((event_box.group_id, filter))
+                .or_insert(next_id);
+
+            for data in &group.data {
+                events.push((lights, event_box.beat + data.beat_offset, data));
+            }
+        }
+    }
+    events.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    // The beat of the next event (strictly after each event) for the same lights, which ends strobes.
+    let mut next_beats: Vec<Option<f32>> = vec![None; events.len()];
+    let mut following: HashMap<usize, (f32, Option<f32>)> = HashMap::new();
+    for (i, (lights, beat, _)) in events.iter().enumerate().rev() {
+        next_beats[i] = match following.get(lights) {
+            Some((next, _)) if next > beat => Some(*next),
+            Some((_, next)) => *next,
+            None => None,
+        };
+        following.insert(*lights, (*beat, next_beats[i]));
+    }
+
+    let last_beat = get_last_beat(difficulty);
+    let mut states: HashMap<usize, (f32, LightColor)> = HashMap::new();
+    let mut flashes = Vec::new();
+
+    for (i, (lights, beat, data)) in events.iter().enumerate() {
+        if let ColorTransitionType::Extend = data.transition_type {
+            continue;
+        }
+
+        let (previous_brightness, previous_color) = states
+            .insert(*lights, (data.brightness, data.color))
+            .unwrap_or((0.0, data.color));
+
+        if let ColorTransitionType::Instant = data.transition_type {
+            let brightened = data.brightness - previous_brightness >= options.min_brightness_change;
+            let recolored = data.color != previous_color
+                && data.brightness >= options.min_brightness_change
+                && previous_brightness >= options.min_brightness_change;
+
+            if brightened || recolored {
+                flashes.push(*beat);
+            }
+        }
+
+        let contrast = (data.brightness - data.get_strobe_off_brightness()).abs();
+        if !data.is_strobing() || contrast < options.min_brightness_change {
+            continue;
+        }
+
+        let end = next_beats[i].unwrap_or(last_beat);
+
+        // Each strobe is computed from the start of the event, to avoid drift and to ensure the loop ends.
+        let frequency = data.strobe_frequency as f32;
+        let count = (((end - beat) * frequency).ceil() as usize).min(MAX_STROBES_PER_EVENT);
+        flashes.extend(
+            (1..count)
+                .map(|n| beat + n as f32 / frequency)
+                .filter(|strobe| strobe < &end),
+        );
+    }
+
+    flashes
+}
+
+/// Returns the beats of every [`ColorBoostEvent`](crate::ColorBoostEvent) that toggles boost colors.
+fn get_boost_flashes(difficulty: &Difficulty) -> Vec<f32> {
+    let mut events: Vec<_> = difficulty.color_boost_events.iter().collect();
+    events.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+    let mut boost = false;
+    let mut flashes = Vec::new();
+
+    for event in events {
+        if event.boost != boost {
+            flashes.push(event.beat);
+            boost = event.boost;
+        }
+    }
+
+    flashes
+}
+
+/// Returns the last beat that any object or event takes place.
+pub(crate) fn get_last_beat(difficulty: &Difficulty) -> f32 {
+    let objects = difficulty.notes.iter().map(Timed::get_beat);
+    let bombs = difficulty.bombs.iter().map(Timed::get_beat);
+    let walls = difficulty.walls.iter().map(Duration::get_end_beat);
+    let arcs = difficulty.arcs.iter().map(Duration::get_end_beat);
+    let chains = difficulty.chains.iter().map(Duration::get_end_beat);
+    let basic = difficulty.basic_events.iter().map(Timed::get_beat);
+    let colors = difficulty.color_event_boxes.iter().map(Timed::get_beat);
+
+    objects
+        .chain(bombs)
+        .chain(walls)
+        .chain(arcs)
+        .chain(chains)
+        .chain(basic)
+        .chain(colors)
+        .fold(0.0, f32::max)
+}
+
+/// Groups sorted flash times (in seconds) into warnings using a sliding window.
+fn group_flashes(
+    flashes: &[f32],
+    timeline: &BpmTimeline,
+    options: &FlashOptions,
+) -> Vec<FlashWarning> {
+    let max_count = options.max_flashes_per_second * options.window;
+    let mut warnings: Vec<FlashWarning> = Vec::new();
+    let mut end = 0;
+
+    for (start, time) in flashes.iter().enumerate() {
+        end = end.max(start);
+        while end < flashes.len() && flashes[end] < time + options.window {
+            end += 1;
+        }
+
+        let count = end - start;
+        if count as f32 <= max_count {
+            continue;
+        }
+
+        let rate = count as f32 / options.window;
+        let last = flashes[end - 1];
+
+        match warnings.last_mut() {
+            Some(warning) if warning.end_seconds >= *time => {
+                if last > warning.end_seconds {
+                    warning.flash_count += flashes[start..end]
+                        .iter()
+                        .filter(|flash| **flash > warning.end_seconds)
+                        .count();
+                    warning.end_seconds = last;
+                }
+                warning.peak_flashes_per_second = warning.peak_flashes_per_second.max(rate);
+            }
+            _ => warnings.push(FlashWarning {
+                start_beat: 0.0,
+                end_beat: 0.0,
+                start_seconds: *time,
+                end_seconds: last,
+                flash_count: count,
+                peak_flashes_per_second: rate,
+                severity: FlashSeverity::Warning,
+            }),
+        }
+    }
+
+    for warning in &mut warnings {
+        warning.start_beat = timeline.get_beat(warning.start_seconds);
+        warning.end_beat = timeline.get_beat(warning.end_seconds);
+
+        if warning.peak_flashes_per_second >= options.severe_flashes_per_second {
+            warning.severity = FlashSeverity::Severe;
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::lightshow::basic::ColorBoostEvent;
+    use crate::difficulty::lightshow::filter::Filter;
+    use crate::difficulty::lightshow::group::color::{ColorEventBox, ColorEventGroup};
+    use crate::loose_bool::LooseBool;
+
+    fn get_basic(beat: f32, value: i32) -> BasicEvent {
+        BasicEvent {
+            beat,
+            event_type: 0,
+            value,
+            float: 1.0,
+        }
+    }
+
+    #[test]
+    fn no_flashes() {
+        let difficulty = Difficulty::default();
+
+        assert!(
+            difficulty
+                .find_flash_warnings(60.0, &FlashOptions::default())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn slow_flashes_are_safe() {
+        let difficulty = Difficulty {
+            basic_events: (0..16).map(|i| get_basic(i as f32, 2)).collect(),
+            ..Default::default()
+        };
+
+        assert!(
+            difficulty
+                .find_flash_warnings(120.0, &FlashOptions::default())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn fast_flashes_warn() {
+        // Four flashes per second at 60 BPM.
+        let difficulty = Difficulty {
+            basic_events: (0..16).map(|i| get_basic(i as f32 * 0.25, 2)).collect(),
+            ..Default::default()
+        };
+
+        let warnings = difficulty.find_flash_warnings(60.0, &FlashOptions::default());
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].start_beat, 0.0);
+        assert_eq!(warnings[0].end_beat, 3.75);
+        assert_eq!(warnings[0].flash_count, 16);
+        assert_eq!(warnings[0].peak_flashes_per_second, 4.0);
+        assert_eq!(warnings[0].severity, FlashSeverity::Warning);
+    }
+
+    #[test]
+    fn repeated_on_is_not_a_flash() {
+        let difficulty = Difficulty {
+            basic_events: (0..16).map(|i| get_basic(i as f32 * 0.25, 1)).collect(),
+            ..Default::default()
+        };
+
+        assert!(
+            difficulty
+                .find_flash_warnings(60.0, &FlashOptions::default())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn simultaneous_flashes_merge() {
+        let difficulty = Difficulty {
+            basic_events: (0..16)
+                .flat_map(|i| {
+                    (0..4).map(move |event_type| BasicEvent {
+                        event_type,
+                        ..get_basic(i as f32, 2)
+                    })
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        assert!(
+            difficulty
+                .find_flash_warnings(120.0, &FlashOptions::default())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn strobe_is_severe() {
+        let difficulty = Difficulty {
+            color_event_boxes: vec![
+                ColorEventBox {
+                    groups: vec![ColorEventGroup {
+                        data: vec![ColorEventData {
+                            strobe_frequency: 8,
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ColorEventBox {
+                    beat: 4.0,
+                    groups: vec![ColorEventGroup {
+                        data: vec![ColorEventData {
+                            brightness: 0.0,
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let warnings = difficulty.find_flash_warnings(60.0, &FlashOptions::default());
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].flash_count, 32);
+        assert_eq!(warnings[0].peak_flashes_per_second, 8.0);
+        assert_eq!(warnings[0].severity, FlashSeverity::Severe);
+    }
+
+    #[test]
+    fn filtered_groups_are_separate() {
+        let get_group = |reverse, brightness| ColorEventGroup {
+            filter: Filter {
+                parameter1: 2,
+                reverse,
+                ..Default::default()
+            },
+            data: vec![ColorEventData {
+                brightness,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        // One half of the lights stays on while the other half stays off.
+        let difficulty = Difficulty {
+            color_event_boxes: (0..16)
+                .map(|i| ColorEventBox {
+                    beat: i as f32 * 0.25,
+                    groups: vec![
+                        get_group(LooseBool::False, 1.0),
+                        get_group(LooseBool::True, 0.0),
+                    ],
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let flashes = get_color_event_flashes(&difficulty, &FlashOptions::default());
+        assert_eq!(flashes, vec![0.0]);
+    }
+
+    #[test]
+    fn extreme_strobe_frequency() {
+        let difficulty = Difficulty {
+            color_event_boxes: vec![ColorEventBox {
+                beat: 1000.0,
+                groups: vec![ColorEventGroup {
+                    data: vec![ColorEventData {
+                        strobe_frequency: i32::MAX,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            notes: vec![crate::difficulty::playfield::Note {
+                beat: 2000.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Strobes this fast are too close together to be distinct flashes, but must not hang.
+        let warnings = difficulty.find_flash_warnings(60.0, &FlashOptions::default());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn boost_toggles() {
+        let difficulty = Difficulty {
+            color_boost_events: (0..8)
+                .map(|i| ColorBoostEvent {
+                    beat: i as f32 * 0.25,
+                    boost: i % 2 == 0,
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let warnings = difficulty.find_flash_warnings(60.0, &FlashOptions::default());
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].flash_count, 8);
+    }
+
+    #[test]
+    fn separate_sections() {
+        let difficulty = Difficulty {
+            basic_events: (0..8)
+                .map(|i| get_basic(i as f32 * 0.25, 2))
+                .chain((0..8).map(|i| get_basic(20.0 + i as f32 * 0.25, 2)))
+                .collect(),
+            ..Default::default()
+        };
+
+        let warnings = difficulty.find_flash_warnings(60.0, &FlashOptions::default());
+
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].end_beat, 1.75);
+        assert_eq!(warnings[1].start_beat, 20.0);
+    }
+}
@@ -0,0 +1,332 @@
+//! Combines the objects of multiple difficulties into one.
+//!
+//! Merged lists stay sorted by beat, with objects from the original difficulty placed before objects on the same beat
+//! from the merged difficulty. Since [`FxEventContainer`](crate::FxEventContainer) stores its event data inside each box
+//! (and only converts it into indexed data when serialized), merged FX boxes always refer to the correct data.
+//!
+//! BPM and lane rotation events that already exist in the original difficulty are skipped,
+//! so merging two difficulties with the same timing doesn't duplicate it.
+//! Any other gameplay events are added as usual, and will affect the timing of both difficulties.
+
+use crate::difficulty::lightshow::EventBox;
+use crate::difficulty::slice::{splice_sorted, splice_sorted_by};
+use crate::difficulty::{Difficulty, ObjectRef};
+use serde::Serialize;
+
+/// The maximum difference between the beats of two conflicting event boxes.
+const BEAT_EPSILON: f32 = 0.001;
+
+/// Controls which parts of a difficulty are [merged](Difficulty::merge_with).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct MergeOptions {
+    /// If true, the interactable objects and gameplay events (BPM and lane rotation events) will be merged.
+    pub playfield: bool,
+    /// If true, the lighting events, waypoints and special event keywords will be merged.
+    pub lightshow: bool,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            playfield: true,
+            lightshow: true,
+        }
+    }
+}
+
+/// The result of [merging](Difficulty::merge_with) two difficulties.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct MergeReport {
+    /// Event boxes of the same type from different difficulties that share a group ID and beat,
+    /// which will override each other in game.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Two event boxes of the same type (one from each difficulty) that share a group ID and beat.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct MergeConflict {
+    /// The earlier box in the merged difficulty.
+    pub first: ObjectRef,
+    /// The later box in the merged difficulty.
+    pub second: ObjectRef,
+    pub group_id: i32,
+    pub beat: f32,
+}
+
+impl Difficulty {
+    /// Adds every object from another difficulty into this one.
+    ///
+    /// For details, see the [module docs](crate::difficulty::merge).
+    pub fn merge(&mut self, other: &Difficulty) -> MergeReport {
+        self.merge_with(other, &MergeOptions::default())
+    }
+
+    /// Adds the objects from another difficulty into this one, using the given options.
+    ///
+    /// For details, see the [module docs](crate::difficulty::merge).
+    pub fn merge_with(&mut self, other: &Difficulty, options: &MergeOptions) -> MergeReport {
+        let other = other.clone();
+
+        if options.playfield {
+            let bpm_events = (other.bpm_events.into_iter())
+                .filter(|event| !self.bpm_events.contains(event))
+                .collect();
+            let lane_rotation_events = (other.lane_rotation_events.into_iter())
+                .filter(|event| !self.lane_rotation_events.contains(event))
+                .collect();
+            splice_sorted(&mut self.bpm_events, bpm_events);
+            splice_sorted(&mut self.lane_rotation_events, lane_rotation_events);
+            splice_sorted(&mut self.notes, other.notes);
+            splice_sorted(&mut self.bombs, other.bombs);
+            splice_sorted(&mut self.walls, other.walls);
+            splice_sorted(&mut self.arcs, other.arcs);
+            splice_sorted(&mut self.chains, other.chains);
+        }
+
+        let mut conflicts = Vec::new();
+        if options.lightshow {
+            splice_sorted(&mut self.waypoints, other.waypoints);
+            splice_sorted(&mut self.basic_events, other.basic_events);
+            splice_sorted(&mut self.color_boost_events, other.color_boost_events);
+            splice_boxes(
+                &mut self.color_event_boxes,
+                other.color_event_boxes,
+                ObjectRef::ColorEventBox,
+                &mut conflicts,
+            );
+            splice_boxes(
+                &mut self.rotation_event_boxes,
+                other.rotation_event_boxes,
+                ObjectRef::RotationEventBox,
+                &mut conflicts,
+            );
+            if let Some(container) = other.fx_event_boxes {
+                splice_boxes(
+                    &mut self.fx_event_boxes.get_or_insert_default().event_boxes,
+                    container.event_boxes,
+                    ObjectRef::FxEventBox,
+                    &mut conflicts,
+                );
+            }
+            if let Some(event_boxes) = other.translation_event_boxes {
+                splice_boxes(
+                    self.translation_event_boxes.get_or_insert_default(),
+                    event_boxes,
+                    ObjectRef::TranslationEventBox,
+                    &mut conflicts,
+                );
+            }
+
+            if let Some(keywords) = other.special_events.keywords {
+                let existing = self.special_events.keywords.get_or_insert_default();
+                for keyword in keywords {
+                    if !existing.contains(&keyword) {
+                        existing.push(keyword);
+                    }
+                }
+            }
+        }
+
+        MergeReport { conflicts }
+    }
+}
+
+/// Adds event boxes to a list in the same order as [`splice_sorted`],
+/// and finds the added boxes that conflict with the existing ones.
+fn splice_boxes<T: EventBox>(
+    event_boxes: &mut Vec<T>,
+    section: Vec<T>,
+    get_ref: fn(usize) -> ObjectRef,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    // Whether each box was added, which stays with the box while merging.
+    let mut tagged: Vec<(bool, T)> = (event_boxes.drain(..))
+        .map(|event_box| (false, event_box))
+        .collect();
+    let section = (section.into_iter())
+        .map(|event_box| (true, event_box))
+        .collect();
+    splice_sorted_by(&mut tagged, section, |(_, event_box)| event_box.get_beat());
+    let added: Vec<bool>;
+    (added, *event_boxes) = tagged.into_iter().unzip();
+
+    find_conflicts(event_boxes, &added, get_ref, conflicts);
+}
+
+/// Finds pairs of existing and added boxes that share a group ID and beat, assuming that the boxes are sorted by beat.
+fn find_conflicts<T: EventBox>(
+    event_boxes: &[T],
+    added: &[bool],
+    get_ref: fn(usize) -> ObjectRef,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    for (i, event_box) in event_boxes.iter().enumerate() {
+        let same_beat = event_boxes[i + 1..]
+            .iter()
+            .enumerate()
+            .take_while(|(_, other)| other.get_beat() - event_box.get_beat() < BEAT_EPSILON);
+
+        for (offset, other) in same_beat {
+            let j = i + 1 + offset;
+            if added[i] != added[j] && other.get_group_id() == event_box.get_group_id() {
+                conflicts.push(MergeConflict {
+                    first: get_ref(i),
+                    second: get_ref(j),
+                    group_id: event_box.get_group_id(),
+                    beat: event_box.get_beat(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::gameplay_event::BpmEvent;
+    use crate::difficulty::lightshow::{ColorEventBox, FxEventBox, FxEventContainer};
+    use crate::difficulty::playfield::Note;
+
+    fn get_color_box(beat: f32, group_id: i32) -> ColorEventBox {
+        ColorEventBox {
+            beat,
+            group_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn playfield_with_lightshow() {
+        let mut playfield = Difficulty {
+            notes: vec![Note::default()],
+            ..Default::default()
+        };
+        let lightshow = Difficulty {
+            notes: vec![Note::default()],
+            color_event_boxes: vec![get_color_box(1.0, 0)],
+            ..Default::default()
+        };
+
+        let options = MergeOptions {
+            playfield: false,
+            lightshow: true,
+        };
+        let report = playfield.merge_with(&lightshow, &options);
+
+        assert!(report.conflicts.is_empty());
+        assert_eq!(playfield.notes.len(), 1);
+        assert_eq!(playfield.color_event_boxes, lightshow.color_event_boxes);
+    }
+
+    #[test]
+    fn boxes_stay_sorted() {
+        let mut first = Difficulty {
+            color_event_boxes: vec![get_color_box(1.0, 0), get_color_box(4.0, 0)],
+            ..Default::default()
+        };
+        let second = Difficulty {
+            color_event_boxes: vec![get_color_box(2.0, 0), get_color_box(4.0, 1)],
+            fx_event_boxes: Some(FxEventContainer {
+                event_boxes: vec![FxEventBox::default()],
+            }),
+            ..Default::default()
+        };
+
+        let report = first.merge(&second);
+        assert!(report.conflicts.is_empty());
+
+        let beats: Vec<(f32, i32)> = (first.color_event_boxes.iter())
+            .map(|event_box| (event_box.beat, event_box.group_id))
+            .collect();
+        assert_eq!(beats, vec![(1.0, 0), (2.0, 0), (4.0, 0), (4.0, 1)]);
+        assert_eq!(first.fx_event_boxes, second.fx_event_boxes);
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut first = Difficulty {
+            color_event_boxes: vec![get_color_box(1.0, 0), get_color_box(2.0, 3)],
+            ..Default::default()
+        };
+        let second = Difficulty {
+            color_event_boxes: vec![get_color_box(2.0, 3)],
+            ..Default::default()
+        };
+
+        let report = first.merge(&second);
+        assert_eq!(
+            report.conflicts,
+            vec![MergeConflict {
+                first: ObjectRef::ColorEventBox(1),
+                second: ObjectRef::ColorEventBox(2),
+                group_id: 3,
+                beat: 2.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn conflicts_between_sources() {
+        // Boxes from the same difficulty are intentional, even if they conflict.
+        let mut first = Difficulty {
+            color_event_boxes: vec![get_color_box(1.0, 0), get_color_box(1.0, 0)],
+            ..Default::default()
+        };
+        let second = Difficulty {
+            color_event_boxes: vec![get_color_box(3.0, 2), get_color_box(3.0, 2)],
+            ..Default::default()
+        };
+        assert!(first.merge(&second).conflicts.is_empty());
+
+        let third = Difficulty {
+            color_event_boxes: vec![get_color_box(1.0002, 0)],
+            ..Default::default()
+        };
+        let report = first.merge(&third);
+        let pairs: Vec<(ObjectRef, ObjectRef)> = (report.conflicts.iter())
+            .map(|conflict| (conflict.first, conflict.second))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (ObjectRef::ColorEventBox(0), ObjectRef::ColorEventBox(2)),
+                (ObjectRef::ColorEventBox(1), ObjectRef::ColorEventBox(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn shared_timing() {
+        let bpm_events = vec![BpmEvent {
+            beat: 4.0,
+            bpm: 60.0,
+        }];
+        let mut first = Difficulty {
+            bpm_events: bpm_events.clone(),
+            ..Default::default()
+        };
+        let second = Difficulty {
+            bpm_events: bpm_events.clone(),
+            ..Default::default()
+        };
+
+        first.merge(&second);
+        assert_eq!(first.bpm_events, bpm_events);
+    }
+}
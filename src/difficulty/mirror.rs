@@ -0,0 +1,274 @@
+//! Horizontally mirrors a difficulty, matching the game's mirror modifier.
+//!
+//! Columns are flipped around the center of the grid, note colors are swapped,
+//! and cut directions and angles are mirrored. Lane rotations are inverted, so that 90/360 degree maps turn the other way.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::gameplay_event::LaneRotationEvent;
+use crate::difficulty::lightshow::EventAxis;
+use crate::difficulty::playfield::{CutDirection, MidAnchorMode, NoteColor};
+use crate::loose_bool::LooseBool;
+
+/// The number of columns in the standard grid.
+const GRID_COLUMNS: i32 = 4;
+
+/// Controls how a difficulty is [mirrored](Difficulty::mirrored_with).
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct MirrorOptions {
+    /// If true, the [axis](crate::RotationEventGroup::invert_axis) of lighting rotation events around the Y and Z axes will be inverted.
+    ///
+    /// This is disabled by default, as the game does not mirror the lightshow.
+    pub mirror_light_rotation: bool,
+}
+
+impl Difficulty {
+    /// Returns a horizontally mirrored copy of the difficulty, leaving the lightshow unchanged.
+    ///
+    /// For details, see the [module docs](crate::difficulty::mirror).
+    pub fn mirrored(&self) -> Self {
+        self.mirrored_with(&MirrorOptions::default())
+    }
+
+    /// Returns a horizontally mirrored copy of the difficulty, using the given options.
+    ///
+    /// For details, see the [module docs](crate::difficulty::mirror).
+    pub fn mirrored_with(&self, options: &MirrorOptions) -> Self {
+        let mut difficulty = self.clone();
+
+        for event in &mut difficulty.lane_rotation_events {
+            event.degrees = -event.degrees;
+        }
+        for event in &mut difficulty.basic_events {
+            // V2 rotation values are symmetric around the center of the range 0..7.
+            if LaneRotationEvent::from_basic_event(event).is_some() {
+                event.value = 7 - event.value;
+            }
+        }
+
+        for note in &mut difficulty.notes {
+            note.col = mirror_col(note.col);
+            note.color = note.color.mirrored();
+            note.direction = note.direction.mirrored();
+            note.angle_offset = -note.angle_offset;
+        }
+
+        for bomb in &mut difficulty.bombs {
+            bomb.col = mirror_col(bomb.col);
+        }
+
+        for wall in &mut difficulty.walls {
+            wall.col = GRID_COLUMNS - wall.col - wall.width;
+        }
+
+        for arc in &mut difficulty.arcs {
+            arc.col = mirror_col(arc.col);
+            arc.tail_col = mirror_col(arc.tail_col);
+            arc.color = arc.color.mirrored();
+            arc.direction = arc.direction.mirrored();
+            arc.tail_direction = arc.tail_direction.mirrored();
+            arc.mid_anchor_mode = arc.mid_anchor_mode.mirrored();
+        }
+
+        for chain in &mut difficulty.chains {
+            chain.col = mirror_col(chain.col);
+            chain.tail_col = mirror_col(chain.tail_col);
+            chain.color = chain.color.mirrored();
+            chain.direction = chain.direction.mirrored();
+        }
+
+        if options.mirror_light_rotation {
+            let groups = difficulty
+                .rotation_event_boxes
+                .iter_mut()
+                .flat_map(|event_box| &mut event_box.groups);
+
+            for group in groups {
+                if matches!(group.axis, EventAxis::Y | EventAxis::Z) {
+                    group.invert_axis = match group.invert_axis {
+                        LooseBool::False => LooseBool::True,
+                        LooseBool::True => LooseBool::False,
+                        undefined => undefined,
+                    };
+                }
+            }
+        }
+
+        difficulty
+    }
+}
+
+fn mirror_col(col: i32) -> i32 {
+    GRID_COLUMNS - 1 - col
+}
+
+impl NoteColor {
+    /// Returns the opposite color, or the same value if undefined.
+    pub fn mirrored(&self) -> Self {
+        match self {
+            NoteColor::Left => NoteColor::Right,
+            NoteColor::Right => NoteColor::Left,
+            other => *other,
+        }
+    }
+}
+
+impl CutDirection {
+    /// Returns the horizontally mirrored direction, i.e. swapping left and right.
+    pub fn mirrored(&self) -> Self {
+        match self {
+            CutDirection::Left => CutDirection::Right,
+            CutDirection::Right => CutDirection::Left,
+            CutDirection::UpLeft => CutDirection::UpRight,
+            CutDirection::UpRight => CutDirection::UpLeft,
+            CutDirection::DownLeft => CutDirection::DownRight,
+            CutDirection::DownRight => CutDirection::DownLeft,
+            other => *other,
+        }
+    }
+}
+
+impl MidAnchorMode {
+    /// Returns the horizontally mirrored mode, i.e. swapping clockwise and counter-clockwise.
+    pub fn mirrored(&self) -> Self {
+        match self {
+            MidAnchorMode::Clockwise => MidAnchorMode::CounterClockwise,
+            MidAnchorMode::CounterClockwise => MidAnchorMode::Clockwise,
+            other => *other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::lightshow::{BasicEvent, RotationEventBox, RotationEventGroup};
+    use crate::difficulty::playfield::{Arc, Chain, Note, Wall};
+
+    #[test]
+    fn notes() {
+        let difficulty = Difficulty {
+            notes: vec![Note {
+                col: 0,
+                color: NoteColor::Left,
+                direction: CutDirection::UpLeft,
+                angle_offset: 15.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let note = &difficulty.mirrored().notes[0];
+        assert_eq!(note.col, 3);
+        assert_eq!(note.color, NoteColor::Right);
+        assert_eq!(note.direction, CutDirection::UpRight);
+        assert_eq!(note.angle_offset, -15.0);
+    }
+
+    #[test]
+    fn walls_by_width() {
+        let difficulty = Difficulty {
+            walls: vec![Wall {
+                col: 0,
+                width: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(difficulty.mirrored().walls[0].col, 2);
+    }
+
+    #[test]
+    fn arcs_and_chains() {
+        let difficulty = Difficulty {
+            arcs: vec![Arc {
+                col: 1,
+                direction: CutDirection::Left,
+                tail_col: 3,
+                tail_direction: CutDirection::DownRight,
+                mid_anchor_mode: MidAnchorMode::Clockwise,
+                ..Default::default()
+            }],
+            chains: vec![Chain {
+                col: 0,
+                color: NoteColor::Right,
+                tail_col: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mirrored = difficulty.mirrored();
+        let arc = &mirrored.arcs[0];
+        assert_eq!((arc.col, arc.tail_col), (2, 0));
+        assert_eq!(arc.direction, CutDirection::Right);
+        assert_eq!(arc.tail_direction, CutDirection::DownLeft);
+        assert_eq!(arc.mid_anchor_mode, MidAnchorMode::CounterClockwise);
+        assert_eq!(arc.color, NoteColor::Right);
+
+        let chain = &mirrored.chains[0];
+        assert_eq!((chain.col, chain.tail_col), (3, 1));
+        assert_eq!(chain.color, NoteColor::Left);
+
+        assert_eq!(mirrored.mirrored(), difficulty);
+    }
+
+    #[test]
+    fn lane_rotation() {
+        let difficulty = Difficulty {
+            lane_rotation_events: vec![LaneRotationEvent {
+                degrees: 45.0,
+                ..Default::default()
+            }],
+            basic_events: vec![BasicEvent {
+                event_type: 14,
+                value: 1,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let timeline = difficulty.mirrored().get_lane_rotation_timeline();
+        assert_eq!(timeline.get_rotation(0.0), -45.0 + 45.0);
+        assert_eq!(timeline.get_events()[0].degrees, -45.0);
+        assert_eq!(timeline.get_events()[1].degrees, 45.0);
+    }
+
+    #[test]
+    fn light_rotation_option() {
+        let difficulty = Difficulty {
+            rotation_event_boxes: vec![RotationEventBox {
+                groups: vec![
+                    RotationEventGroup {
+                        axis: EventAxis::X,
+                        ..Default::default()
+                    },
+                    RotationEventGroup {
+                        axis: EventAxis::Y,
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            difficulty.mirrored().rotation_event_boxes,
+            difficulty.rotation_event_boxes
+        );
+
+        let options = MirrorOptions {
+            mirror_light_rotation: true,
+        };
+        let mirrored = difficulty.mirrored_with(&options);
+        let groups = &mirrored.rotation_event_boxes[0].groups;
+        assert_eq!(groups[0].invert_axis, LooseBool::False);
+        assert_eq!(groups[1].invert_axis, LooseBool::True);
+    }
+}
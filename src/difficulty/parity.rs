@@ -0,0 +1,539 @@
+//! Infers whether each swing is a forehand or backhand, and flags swings that break the flow of a map.
+//!
+//! Each saber is checked separately, walking its swings in time order:
+//! - [`Note`]s are swings. Notes of the same color that are (almost) at the same time, such as stacks and sliders,
+//!   are treated as a single swing.
+//! - [`Chain`]s are swings if there is no note of the same color at their head.
+//!   [`Arc`](crate::difficulty::playfield::Arc)s are never cut, so they are not swings.
+//!
+//! After a forehand, the next swing is expected to be a backhand and vice versa.
+//! Swinging the same way twice in a row requires the player to reset their saber, which is only expected
+//! if a [`Bomb`] is placed where the saber rests after the previous swing, or after a long gap.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::playfield::{Bomb, Chain, CutDirection, Note, NoteColor};
+use serde::Serialize;
+
+/// Notes of the same color that are at most this many beats apart are treated as a single swing.
+const STACK_THRESHOLD: f32 = 0.0625 + 0.001;
+/// The number of beats between swings after which the player may reset their saber without a bomb.
+const RESET_GAP: f32 = 2.0;
+
+/// The direction that a saber is swung.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum Parity {
+    /// A swing with the palm leading, usually downwards.
+    Forehand,
+    /// A swing with the back of the hand leading, usually upwards.
+    Backhand,
+}
+
+impl Parity {
+    /// Returns the opposite parity.
+    pub fn flipped(&self) -> Self {
+        match self {
+            Parity::Forehand => Parity::Backhand,
+            Parity::Backhand => Parity::Forehand,
+        }
+    }
+
+    /// Returns the natural parity for cutting in a direction with a given saber.
+    ///
+    /// Returns `None` if the cut direction is any/undefined.
+    pub fn from_cut(color: NoteColor, direction: CutDirection, angle_offset: f32) -> Option<Self> {
+        if matches!(direction, CutDirection::Any | CutDirection::Undefined(_)) {
+            return None;
+        }
+
+        // Zero degrees is a downward cut.
+        let angle = (direction.get_degrees() + angle_offset + 180.0).rem_euclid(360.0) - 180.0;
+
+        if (angle.abs() - 90.0).abs() < 0.001 {
+            // Horizontal cuts towards the body are forehands, such as a right saber cutting left.
+            let towards_left = angle < 0.0;
+            return Some(match (color, towards_left) {
+                (NoteColor::Left, true) | (NoteColor::Right, false) => Parity::Backhand,
+                _ => Parity::Forehand,
+            });
+        }
+
+        if angle.abs() < 90.0 {
+            Some(Parity::Forehand)
+        } else {
+            Some(Parity::Backhand)
+        }
+    }
+}
+
+/// Whether a swing follows the flow of the map.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum ParityStatus {
+    /// The swing follows the previous swing.
+    Ok,
+    /// The swing has the same parity as the previous swing, without anything to reset the saber.
+    Break,
+    /// The swing has the same parity as the previous swing, but a bomb or a long gap resets the saber.
+    Reset,
+    /// A dot note whose parity can not be reliably inferred, because the player may have reset during a long gap.
+    QuestionableDot,
+}
+
+/// The object that caused a [`Swing`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum SwingSource {
+    /// An index into [`Difficulty::notes`].
+    Note(usize),
+    /// An index into [`Difficulty::chains`].
+    Chain(usize),
+}
+
+/// The inferred parity of a single object.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct Swing {
+    pub source: SwingSource,
+    /// The beat of the object.
+    pub beat: f32,
+    /// The saber used to cut the object.
+    pub color: NoteColor,
+    pub parity: Parity,
+    pub status: ParityStatus,
+}
+
+/// An object that can be swung at, before its parity is known.
+struct SwingObject {
+    source: SwingSource,
+    beat: f32,
+    col: i32,
+    parity: Option<Parity>,
+}
+
+impl Difficulty {
+    /// Infers the parity of each note, as well as chains without a head note, sorted by beat.
+    ///
+    /// For details on how parity is inferred, see the [module docs](crate::difficulty::parity).
+    pub fn get_parity(&self) -> Vec<Swing> {
+        let mut swings = get_color_parity(self, NoteColor::Left);
+        swings.extend(get_color_parity(self, NoteColor::Right));
+        swings.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        swings
+    }
+}
+
+fn get_color_parity(difficulty: &Difficulty, color: NoteColor) -> Vec<Swing> {
+    let notes: Vec<(usize, &Note)> = difficulty
+        .notes
+        .iter()
+        .enumerate()
+        .filter(|(_, note)| note.color == color)
+        .collect();
+
+    let has_head_note = |beat: f32, row: i32, col: i32| {
+        notes.iter().any(|(_, note)| {
+            (note.beat - beat).abs() <= STACK_THRESHOLD && note.row == row && note.col == col
+        })
+    };
+
+    let mut objects: Vec<SwingObject> = notes
+        .iter()
+        .map(|(i, note)| SwingObject {
+            source: SwingSource::Note(*i),
+            beat: note.beat,
+            col: note.col,
+            parity: Parity::from_cut(color, note.direction, note.angle_offset),
+        })
+        .collect();
+
+    objects.extend(
+        difficulty
+            .chains
+            .iter()
+            .enumerate()
+            .filter(|(_, chain)| {
+                chain.color == color && !has_head_note(chain.beat, chain.row, chain.col)
+            })
+            .map(|(i, chain): (usize, &Chain)| SwingObject {
+                source: SwingSource::Chain(i),
+                beat: chain.beat,
+                col: chain.col,
+                parity: Parity::from_cut(color, chain.direction, 0.0),
+            }),
+    );
+
+    objects.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+    let mut bombs: Vec<&Bomb> = difficulty.bombs.iter().collect();
+    bombs.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+    let mut swings = Vec::with_capacity(objects.len());
+    let mut previous: Option<(Parity, f32, i32)> = None;
+    let mut start = 0;
+
+    while start < objects.len() {
+        // Group stacks and sliders into a single swing.
+        let mut end = start + 1;
+        while end < objects.len() && objects[end].beat - objects[end - 1].beat <= STACK_THRESHOLD {
+            end += 1;
+        }
+        let group = &objects[start..end];
+        start = end;
+
+        let beat = group[0].beat;
+        let actual = group.iter().find_map(|object| object.parity);
+
+        let (parity, status) = match previous {
+            None => (actual.unwrap_or(Parity::Forehand), ParityStatus::Ok),
+            Some((last_parity, last_beat, last_col)) => {
+                let long_gap = beat - last_beat >= RESET_GAP;
+                let bomb_reset = has_bomb_reset(&bombs, last_parity, last_beat, beat, last_col);
+
+                match actual {
+                    Some(parity) if parity != last_parity => (parity, ParityStatus::Ok),
+                    Some(parity) if bomb_reset || long_gap => (parity, ParityStatus::Reset),
+                    Some(parity) => (parity, ParityStatus::Break),
+                    None if bomb_reset => (last_parity, ParityStatus::Reset),
+                    None if long_gap => (last_parity.flipped(), ParityStatus::QuestionableDot),
+                    None => (last_parity.flipped(), ParityStatus::Ok),
+                }
+            }
+        };
+
+        let last = group.last().expect("Groups are never empty");
+        previous = Some((parity, last.beat, last.col));
+
+        swings.extend(group.iter().map(|object| Swing {
+            source: object.source,
+            beat: object.beat,
+            color,
+            parity,
+            status,
+        }));
+    }
+
+    swings
+}
+
+/// Returns true if there is a bomb between two swings that is placed where the saber rests after the first swing.
+///
+/// After a forehand, the saber rests low, so a bomb on the bottom row forces it back up, and vice versa.
+fn has_bomb_reset(
+    bombs: &[&Bomb],
+    last_parity: Parity,
+    last_beat: f32,
+    beat: f32,
+    last_col: i32,
+) -> bool {
+    let rest_row = match last_parity {
+        Parity::Forehand => 0,
+        Parity::Backhand => 2,
+    };
+
+    let first = bombs.partition_point(|bomb| bomb.beat <= last_beat);
+
+    bombs[first..]
+        .iter()
+        .take_while(|bomb| bomb.beat < beat)
+        .any(|bomb| bomb.row == rest_row && (bomb.col - last_col).abs() <= 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::playfield::Arc;
+
+    fn get_note(beat: f32, color: NoteColor, direction: CutDirection) -> Note {
+        Note {
+            beat,
+            row: 1,
+            col: 1,
+            color,
+            direction,
+            angle_offset: 0.0,
+        }
+    }
+
+    fn get_statuses(difficulty: &Difficulty) -> Vec<(Parity, ParityStatus)> {
+        difficulty
+            .get_parity()
+            .iter()
+            .map(|swing| (swing.parity, swing.status))
+            .collect()
+    }
+
+    #[test]
+    fn cut_parity() {
+        use CutDirection::*;
+        let left = NoteColor::Left;
+        let right = NoteColor::Right;
+
+        assert_eq!(Parity::from_cut(left, Down, 0.0), Some(Parity::Forehand));
+        assert_eq!(
+            Parity::from_cut(left, DownLeft, 0.0),
+            Some(Parity::Forehand)
+        );
+        assert_eq!(Parity::from_cut(left, Up, 0.0), Some(Parity::Backhand));
+        assert_eq!(Parity::from_cut(left, UpRight, 0.0), Some(Parity::Backhand));
+        assert_eq!(Parity::from_cut(left, Any, 0.0), None);
+
+        assert_eq!(Parity::from_cut(right, Left, 0.0), Some(Parity::Forehand));
+        assert_eq!(Parity::from_cut(right, Right, 0.0), Some(Parity::Backhand));
+        assert_eq!(Parity::from_cut(left, Right, 0.0), Some(Parity::Forehand));
+        assert_eq!(Parity::from_cut(left, Left, 0.0), Some(Parity::Backhand));
+    }
+
+    #[test]
+    fn angle_offset_changes_parity() {
+        let right = NoteColor::Right;
+
+        assert_eq!(
+            Parity::from_cut(right, CutDirection::Down, 100.0),
+            Some(Parity::Backhand)
+        );
+        assert_eq!(
+            Parity::from_cut(right, CutDirection::Up, 100.0),
+            Some(Parity::Forehand)
+        );
+    }
+
+    #[test]
+    fn alternating_flow() {
+        let difficulty = Difficulty {
+            notes: (0..8)
+                .map(|i| {
+                    let direction = if i % 2 == 0 {
+                        CutDirection::Down
+                    } else {
+                        CutDirection::Up
+                    };
+                    get_note(i as f32, NoteColor::Right, direction)
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let swings = difficulty.get_parity();
+        assert_eq!(swings.len(), 8);
+        assert!(swings.iter().all(|swing| swing.status == ParityStatus::Ok));
+        assert_eq!(swings[1].parity, Parity::Backhand);
+    }
+
+    #[test]
+    fn parity_break() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Right, CutDirection::Down),
+                get_note(1.0, NoteColor::Right, CutDirection::Down),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_statuses(&difficulty),
+            vec![
+                (Parity::Forehand, ParityStatus::Ok),
+                (Parity::Forehand, ParityStatus::Break),
+            ]
+        );
+    }
+
+    #[test]
+    fn colors_are_separate() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Right, CutDirection::Down),
+                get_note(0.5, NoteColor::Left, CutDirection::Down),
+                get_note(1.0, NoteColor::Right, CutDirection::Up),
+                get_note(1.5, NoteColor::Left, CutDirection::Up),
+            ],
+            ..Default::default()
+        };
+
+        assert!(
+            difficulty
+                .get_parity()
+                .iter()
+                .all(|swing| swing.status == ParityStatus::Ok)
+        );
+    }
+
+    #[test]
+    fn bomb_reset() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Right, CutDirection::Down),
+                get_note(1.0, NoteColor::Right, CutDirection::Down),
+            ],
+            bombs: vec![Bomb {
+                beat: 0.5,
+                row: 0,
+                col: 1,
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_statuses(&difficulty)[1],
+            (Parity::Forehand, ParityStatus::Reset)
+        );
+    }
+
+    #[test]
+    fn far_bomb_is_not_reset() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Right, CutDirection::Down),
+                get_note(1.0, NoteColor::Right, CutDirection::Down),
+            ],
+            bombs: vec![Bomb {
+                beat: 0.5,
+                row: 2,
+                col: 1,
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_statuses(&difficulty)[1],
+            (Parity::Forehand, ParityStatus::Break)
+        );
+    }
+
+    #[test]
+    fn long_gap_reset() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Right, CutDirection::Down),
+                get_note(4.0, NoteColor::Right, CutDirection::Down),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_statuses(&difficulty)[1],
+            (Parity::Forehand, ParityStatus::Reset)
+        );
+    }
+
+    #[test]
+    fn dots_follow_flow() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Right, CutDirection::Down),
+                get_note(1.0, NoteColor::Right, CutDirection::Any),
+                get_note(2.0, NoteColor::Right, CutDirection::Any),
+                get_note(6.0, NoteColor::Right, CutDirection::Any),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_statuses(&difficulty),
+            vec![
+                (Parity::Forehand, ParityStatus::Ok),
+                (Parity::Backhand, ParityStatus::Ok),
+                (Parity::Forehand, ParityStatus::Ok),
+                (Parity::Backhand, ParityStatus::QuestionableDot),
+            ]
+        );
+    }
+
+    #[test]
+    fn stacks_are_one_swing() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Right, CutDirection::Down),
+                Note {
+                    row: 0,
+                    ..get_note(0.0, NoteColor::Right, CutDirection::Down)
+                },
+                get_note(1.0, NoteColor::Right, CutDirection::Up),
+            ],
+            ..Default::default()
+        };
+
+        assert!(
+            difficulty
+                .get_parity()
+                .iter()
+                .all(|swing| swing.status == ParityStatus::Ok)
+        );
+    }
+
+    #[test]
+    fn chain_with_head_note_is_not_a_swing() {
+        let difficulty = Difficulty {
+            notes: vec![get_note(0.0, NoteColor::Right, CutDirection::Down)],
+            chains: vec![
+                Chain {
+                    beat: 0.0,
+                    row: 1,
+                    col: 1,
+                    color: NoteColor::Right,
+                    direction: CutDirection::Down,
+                    tail_beat: 0.5,
+                    ..Default::default()
+                },
+                Chain {
+                    beat: 1.0,
+                    row: 1,
+                    col: 1,
+                    color: NoteColor::Right,
+                    direction: CutDirection::Up,
+                    tail_beat: 1.5,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let swings = difficulty.get_parity();
+        assert_eq!(swings.len(), 2);
+        assert_eq!(swings[0].source, SwingSource::Note(0));
+        assert_eq!(swings[1].source, SwingSource::Chain(1));
+        assert_eq!(swings[1].status, ParityStatus::Ok);
+    }
+
+    #[test]
+    fn arcs_are_not_swings() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Right, CutDirection::Down),
+                get_note(2.0, NoteColor::Right, CutDirection::Up),
+            ],
+            arcs: vec![Arc {
+                beat: 1.0,
+                row: 1,
+                col: 1,
+                color: NoteColor::Right,
+                direction: CutDirection::Down,
+                tail_beat: 1.5,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let swings = difficulty.get_parity();
+        assert_eq!(swings.len(), 2);
+        assert_eq!(swings[1].source, SwingSource::Note(1));
+        assert_eq!(swings[1].status, ParityStatus::Ok);
+    }
+}
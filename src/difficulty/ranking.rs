@@ -0,0 +1,547 @@
+//! Checks a difficulty against the criteria that ranked leaderboards use to reject maps.
+//!
+//! Unlike [validation](Difficulty::validate), these checks depend on timing, so they need the map's BPM and NJS.
+//! Each rule can be configured or disabled using [`RankingRules`].
+
+use crate::difficulty::bpm::BpmTimeline;
+use crate::difficulty::playfield::NoteColor;
+use crate::difficulty::{Difficulty, ObjectRef};
+use crate::info::DifficultyInfo;
+use crate::spatial::{Vec3, get_lane_x, get_layer_y};
+use crate::timing_traits::Duration;
+use serde::Serialize;
+
+/// Controls which [ranking criteria](crate::difficulty::ranking) are checked, and their thresholds.
+///
+/// All times are in seconds. Setting a rule to `None` disables it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct RankingRules {
+    /// The time after a note or bomb in the center of the grid where other objects are considered hidden behind it.
+    ///
+    /// Objects closer than [`vision_block_min`](Self::vision_block_min) are not counted, as they are visible together.
+    pub vision_block_max: Option<f32>,
+    /// The time (in seconds) after a vision blocking object where other objects start to be counted as hidden.
+    ///
+    /// Objects that come sooner than this are seen at the same time as the blocking object, so they are not hidden by it.
+    pub vision_block_min: f32,
+    /// Whether to check for notes that are inside of walls.
+    pub notes_in_walls: bool,
+    /// The size (in meters) of a note or bomb's hitbox.
+    ///
+    /// Notes of different colors, or notes and bombs, are not allowed to have overlapping hitboxes.
+    pub hitbox_size: Option<Vec3>,
+    /// The minimum duration of a wall.
+    pub min_wall_duration: Option<f32>,
+    /// The time at the start of the song that must not contain any objects.
+    pub start_buffer: Option<f32>,
+    /// The time at the end of the song that must not contain any objects.
+    pub end_buffer: Option<f32>,
+}
+
+impl Default for RankingRules {
+    fn default() -> Self {
+        Self {
+            vision_block_max: Some(0.5),
+            vision_block_min: 0.1,
+            notes_in_walls: true,
+            hitbox_size: Some(Vec3::new(0.5, 0.5, 0.5)),
+            min_wall_duration: Some(0.015),
+            start_buffer: Some(1.5),
+            end_buffer: Some(1.0),
+        }
+    }
+}
+
+/// The result of [checking](Difficulty::check_ranking) a difficulty against the [`RankingRules`].
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct RankingReport {
+    /// Every rule that was broken, sorted by time.
+    pub violations: Vec<RankingViolation>,
+}
+
+impl RankingReport {
+    /// Returns true if no rules were broken.
+    pub fn is_passing(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// A single broken [ranking rule](RankingRules).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct RankingViolation {
+    /// The object that broke the rule.
+    pub object: ObjectRef,
+    /// The beat of the object.
+    pub beat: f32,
+    /// The time of the object, in seconds.
+    pub seconds: f32,
+    pub kind: RankingViolationKind,
+}
+
+/// The [ranking rule](RankingRules) that was broken.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum RankingViolationKind {
+    /// The object is in the center of the grid, and hides another object behind it.
+    VisionBlock { blocked: ObjectRef },
+    /// The note is inside of a wall.
+    NoteInWall { wall: ObjectRef },
+    /// The object's hitbox overlaps with a note of a different color, or a bomb.
+    HitboxOverlap { other: ObjectRef },
+    /// The wall is shorter than the [minimum duration](RankingRules::min_wall_duration).
+    ShortWall { duration_seconds: f32 },
+    /// The object is within the [start buffer](RankingRules::start_buffer).
+    TooCloseToStart,
+    /// The object is within the [end buffer](RankingRules::end_buffer).
+    TooCloseToEnd,
+}
+
+/// A note or bomb, with its time and position.
+struct GridObject {
+    object: ObjectRef,
+    beat: f32,
+    seconds: f32,
+    row: i32,
+    col: i32,
+    /// The note color, or `None` for bombs.
+    color: Option<NoteColor>,
+}
+
+impl Difficulty {
+    /// Checks the difficulty against the criteria that ranked leaderboards use to reject maps.
+    ///
+    /// The base BPM is defined by the map's [info file](crate::info::Beatmap::bpm),
+    /// and the song duration (in seconds) is needed for the [end buffer](RankingRules::end_buffer).
+    pub fn check_ranking(
+        &self,
+        info: &DifficultyInfo,
+        base_bpm: f32,
+        song_duration: f32,
+        rules: &RankingRules,
+    ) -> RankingReport {
+        let timeline = self.get_bpm_timeline(base_bpm);
+        let objects = get_grid_objects(self, &timeline);
+        let mut violations = Vec::new();
+
+        if let Some(max) = rules.vision_block_max {
+            check_vision_blocks(&objects, rules.vision_block_min, max, &mut violations);
+        }
+        if rules.notes_in_walls {
+            check_notes_in_walls(self, &timeline, &objects, &mut violations);
+        }
+        if let Some(size) = rules.hitbox_size {
+            check_hitboxes(&objects, info.get_njs(), size, &mut violations);
+        }
+        if let Some(min_duration) = rules.min_wall_duration {
+            check_wall_durations(self, &timeline, min_duration, &mut violations);
+        }
+        check_buffers(self, &timeline, song_duration, rules, &mut violations);
+
+        violations.sort_by(|a, b| a.seconds.total_cmp(&b.seconds));
+        RankingReport { violations }
+    }
+}
+
+/// Returns every note and bomb, sorted by time.
+fn get_grid_objects(difficulty: &Difficulty, timeline: &BpmTimeline) -> Vec<GridObject> {
+    let notes = difficulty
+        .notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| GridObject {
+            object: ObjectRef::Note(i),
+            beat: note.beat,
+            seconds: timeline.get_seconds(note.beat),
+            row: note.row,
+            col: note.col,
+            color: Some(note.color),
+        });
+    let bombs = difficulty
+        .bombs
+        .iter()
+        .enumerate()
+        .map(|(i, bomb)| GridObject {
+            object: ObjectRef::Bomb(i),
+            beat: bomb.beat,
+            seconds: timeline.get_seconds(bomb.beat),
+            row: bomb.row,
+            col: bomb.col,
+            color: None,
+        });
+
+    let mut objects: Vec<GridObject> = notes.chain(bombs).collect();
+    objects.sort_by(|a, b| a.seconds.total_cmp(&b.seconds));
+    objects
+}
+
+fn push_violation(
+    violations: &mut Vec<RankingViolation>,
+    object: &GridObject,
+    kind: RankingViolationKind,
+) {
+    violations.push(RankingViolation {
+        object: object.object,
+        beat: object.beat,
+        seconds: object.seconds,
+        kind,
+    });
+}
+
+fn check_vision_blocks(
+    objects: &[GridObject],
+    min: f32,
+    max: f32,
+    violations: &mut Vec<RankingViolation>,
+) {
+    for (i, blocker) in objects.iter().enumerate() {
+        if blocker.row != 1 || !(1..=2).contains(&blocker.col) {
+            continue;
+        }
+
+        let blocked = objects[i + 1..]
+            .iter()
+            .take_while(|other| other.seconds - blocker.seconds <= max)
+            .find(|other| other.seconds - blocker.seconds >= min);
+
+        if let Some(blocked) = blocked {
+            let kind = RankingViolationKind::VisionBlock {
+                blocked: blocked.object,
+            };
+            push_violation(violations, blocker, kind);
+        }
+    }
+}
+
+fn check_notes_in_walls(
+    difficulty: &Difficulty,
+    timeline: &BpmTimeline,
+    objects: &[GridObject],
+    violations: &mut Vec<RankingViolation>,
+) {
+    for note in objects.iter().filter(|object| object.color.is_some()) {
+        let wall = difficulty.walls.iter().position(|wall| {
+            let start = timeline.get_seconds(wall.beat);
+            let end = timeline.get_seconds(wall.get_end_beat());
+
+            (start..=end).contains(&note.seconds)
+                && (wall.col..wall.col + wall.width).contains(&note.col)
+                && (wall.row..wall.row + wall.height).contains(&note.row)
+        });
+
+        if let Some(wall) = wall {
+            let kind = RankingViolationKind::NoteInWall {
+                wall: ObjectRef::Wall(wall),
+            };
+            push_violation(violations, note, kind);
+        }
+    }
+}
+
+fn check_hitboxes(
+    objects: &[GridObject],
+    njs: f32,
+    size: Vec3,
+    violations: &mut Vec<RankingViolation>,
+) {
+    for (i, object) in objects.iter().enumerate() {
+        let other = objects[i + 1..]
+            .iter()
+            .take_while(|other| (other.seconds - object.seconds) * njs < size.z)
+            .find(|other| {
+                let conflicting = match (object.color, other.color) {
+                    (Some(color), Some(other_color)) => color != other_color,
+                    (None, None) => false,
+                    _ => true,
+                };
+                let dx = get_lane_x(other.col as f32) - get_lane_x(object.col as f32);
+                let dy = get_layer_y(other.row as f32) - get_layer_y(object.row as f32);
+
+                conflicting && dx.abs() < size.x && dy.abs() < size.y
+            });
+
+        if let Some(other) = other {
+            let kind = RankingViolationKind::HitboxOverlap {
+                other: other.object,
+            };
+            push_violation(violations, object, kind);
+        }
+    }
+}
+
+fn check_wall_durations(
+    difficulty: &Difficulty,
+    timeline: &BpmTimeline,
+    min_duration: f32,
+    violations: &mut Vec<RankingViolation>,
+) {
+    for (i, wall) in difficulty.walls.iter().enumerate() {
+        let seconds = timeline.get_seconds(wall.beat);
+        let duration_seconds = timeline.get_seconds(wall.get_end_beat()) - seconds;
+
+        if duration_seconds < min_duration {
+            violations.push(RankingViolation {
+                object: ObjectRef::Wall(i),
+                beat: wall.beat,
+                seconds,
+                kind: RankingViolationKind::ShortWall { duration_seconds },
+            });
+        }
+    }
+}
+
+fn check_buffers(
+    difficulty: &Difficulty,
+    timeline: &BpmTimeline,
+    song_duration: f32,
+    rules: &RankingRules,
+    violations: &mut Vec<RankingViolation>,
+) {
+    let mut spans: Vec<(ObjectRef, f32, f32)> = Vec::new();
+    spans.extend(
+        (difficulty.notes.iter().enumerate())
+            .map(|(i, note)| (ObjectRef::Note(i), note.beat, note.beat)),
+    );
+    spans.extend(
+        (difficulty.bombs.iter().enumerate())
+            .map(|(i, bomb)| (ObjectRef::Bomb(i), bomb.beat, bomb.beat)),
+    );
+    spans.extend(
+        (difficulty.walls.iter().enumerate())
+            .map(|(i, wall)| (ObjectRef::Wall(i), wall.beat, wall.get_end_beat())),
+    );
+    spans.extend(
+        (difficulty.arcs.iter().enumerate())
+            .map(|(i, arc)| (ObjectRef::Arc(i), arc.beat, arc.tail_beat)),
+    );
+    spans.extend(
+        (difficulty.chains.iter().enumerate())
+            .map(|(i, chain)| (ObjectRef::Chain(i), chain.beat, chain.tail_beat)),
+    );
+
+    for (object, beat, end_beat) in spans {
+        let seconds = timeline.get_seconds(beat);
+
+        let kind = if rules.start_buffer.is_some_and(|buffer| seconds < buffer) {
+            RankingViolationKind::TooCloseToStart
+        } else if rules
+            .end_buffer
+            .is_some_and(|buffer| timeline.get_seconds(end_beat) > song_duration - buffer)
+        {
+            RankingViolationKind::TooCloseToEnd
+        } else {
+            continue;
+        };
+
+        violations.push(RankingViolation {
+            object,
+            beat,
+            seconds,
+            kind,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::playfield::{Bomb, Note, Wall};
+
+    fn check(difficulty: &Difficulty, rules: &RankingRules) -> Vec<RankingViolationKind> {
+        let info = DifficultyInfo {
+            njs: 10.0,
+            ..Default::default()
+        };
+
+        difficulty
+            .check_ranking(&info, 60.0, 100.0, rules)
+            .violations
+            .into_iter()
+            .map(|violation| violation.kind)
+            .collect()
+    }
+
+    fn get_note(beat: f32, row: i32, col: i32, color: NoteColor) -> Note {
+        Note {
+            beat,
+            row,
+            col,
+            color,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn passing() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(10.0, 0, 1, NoteColor::Left),
+                get_note(10.0, 0, 2, NoteColor::Right),
+            ],
+            walls: vec![Wall {
+                beat: 11.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let info = DifficultyInfo::default();
+        let report = difficulty.check_ranking(&info, 60.0, 100.0, &RankingRules::default());
+        assert!(report.is_passing());
+    }
+
+    #[test]
+    fn vision_block() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(10.0, 1, 1, NoteColor::Left),
+                get_note(10.25, 0, 0, NoteColor::Left),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check(&difficulty, &RankingRules::default()),
+            vec![RankingViolationKind::VisionBlock {
+                blocked: ObjectRef::Note(1)
+            }]
+        );
+
+        let rules = RankingRules {
+            vision_block_max: None,
+            ..Default::default()
+        };
+        assert_eq!(check(&difficulty, &rules), vec![]);
+    }
+
+    #[test]
+    fn note_in_wall() {
+        let difficulty = Difficulty {
+            notes: vec![get_note(10.5, 0, 1, NoteColor::Left)],
+            walls: vec![Wall {
+                beat: 10.0,
+                col: 1,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check(&difficulty, &RankingRules::default()),
+            vec![RankingViolationKind::NoteInWall {
+                wall: ObjectRef::Wall(0)
+            }]
+        );
+    }
+
+    #[test]
+    fn hitbox_overlap() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(10.0, 0, 0, NoteColor::Left),
+                get_note(10.02, 0, 0, NoteColor::Right),
+                // Sliders of the same color are allowed.
+                get_note(20.0, 0, 0, NoteColor::Left),
+                get_note(20.02, 0, 0, NoteColor::Left),
+            ],
+            bombs: vec![Bomb {
+                beat: 30.0,
+                row: 0,
+                col: 3,
+            }],
+            ..Default::default()
+        };
+
+        let mut difficulty_with_bomb = difficulty.clone();
+        difficulty_with_bomb
+            .notes
+            .push(get_note(30.0, 0, 3, NoteColor::Right));
+
+        assert_eq!(
+            check(&difficulty, &RankingRules::default()),
+            vec![RankingViolationKind::HitboxOverlap {
+                other: ObjectRef::Note(1)
+            }]
+        );
+        assert_eq!(
+            check(&difficulty_with_bomb, &RankingRules::default()).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn short_wall() {
+        let difficulty = Difficulty {
+            walls: vec![Wall {
+                beat: 10.0,
+                duration: 0.0078125,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check(&difficulty, &RankingRules::default()),
+            vec![RankingViolationKind::ShortWall {
+                duration_seconds: 0.0078125
+            }]
+        );
+    }
+
+    #[test]
+    fn start_and_end_buffers() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(1.0, 0, 0, NoteColor::Left),
+                get_note(99.5, 0, 0, NoteColor::Left),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check(&difficulty, &RankingRules::default()),
+            vec![
+                RankingViolationKind::TooCloseToStart,
+                RankingViolationKind::TooCloseToEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn serializes_report() {
+        let report = RankingReport {
+            violations: vec![RankingViolation {
+                object: ObjectRef::Wall(2),
+                beat: 4.0,
+                seconds: 2.0,
+                kind: RankingViolationKind::ShortWall {
+                    duration_seconds: 0.01,
+                },
+            }],
+        };
+
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"violations":[{"object":{"Wall":2},"beat":4.0,"seconds":2.0,"kind":{"type":"ShortWall","durationSeconds":0.01}}]}"#
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! Moves every object in a difficulty through time at once.
+//!
+//! These operations update the beat of every object, the end beats of walls, arcs and chains,
+//! and the beats of lighting event boxes (including FX boxes).
+//! Scaling and remapping also update the relative beats inside event boxes, such as event data offsets
+//! and [beat distributions](crate::ColorEventGroup::beat_dist_value), which are treated as an offset from the start of the box.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::bpm::BpmTimeline;
+use crate::difficulty::gameplay_event::BpmEvent;
+
+impl Difficulty {
+    /// Moves every object by a number of beats, which can be negative.
+    ///
+    /// Relative beats inside event boxes are unchanged, as they move with their box.
+    pub fn shift_beats(&mut self, offset: f32) {
+        self.map_beats(|_, beat| beat + offset);
+    }
+
+    /// Multiplies the beat of every object by a factor, including durations and relative beats.
+    /// The BPM of every [`BpmEvent`] is multiplied by the same factor, so that objects stay at the same time (in seconds).
+    ///
+    /// For example, a factor of two will keep a map in time after doubling the base BPM in its [info file](crate::info::Beatmap::bpm).
+    /// # Panics
+    /// Will panic if the factor is not a positive, finite number.
+    pub fn scale_beats(&mut self, factor: f32) {
+        assert!(
+            factor.is_finite() && factor > 0.0,
+            "scale factor must be positive and finite, got {factor}"
+        );
+
+        self.map_relative_beats(|_, offset| offset * factor);
+        self.map_beats(|_, beat| beat * factor);
+        for event in &mut self.bpm_events {
+            event.bpm *= factor;
+        }
+    }
+
+    /// Replaces the difficulty's [`BpmEvent`]s, moving every object so that it stays at the same time (in seconds).
+    ///
+    /// The base BPMs are defined by the map's [info file](crate::info::Beatmap::bpm),
+    /// and may be different if the info file is also being updated.
+    pub fn remap_bpm(&mut self, base_bpm: f32, new_base_bpm: f32, bpm_events: Vec<BpmEvent>) {
+        let old_timeline = self.get_bpm_timeline(base_bpm);
+        let new_timeline = BpmTimeline::new(new_base_bpm, &bpm_events);
+        let remap = |beat: f32| new_timeline.get_beat(old_timeline.get_seconds(beat));
+
+        self.map_relative_beats(|start, offset| remap(start + offset) - remap(start));
+        self.map_beats(|_, beat| remap(beat));
+        self.bpm_events = bpm_events;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::lightshow::{ColorEventBox, ColorEventData, ColorEventGroup};
+    use crate::difficulty::playfield::{Arc, Note, Wall};
+
+    fn get_difficulty() -> Difficulty {
+        Difficulty {
+            notes: vec![Note {
+                beat: 4.0,
+                ..Default::default()
+            }],
+            walls: vec![Wall {
+                beat: 2.0,
+                duration: 4.0,
+                ..Default::default()
+            }],
+            arcs: vec![Arc {
+                beat: 1.0,
+                tail_beat: 3.0,
+                ..Default::default()
+            }],
+            color_event_boxes: vec![ColorEventBox {
+                beat: 2.0,
+                groups: vec![ColorEventGroup {
+                    beat_dist_value: 1.0,
+                    data: vec![ColorEventData {
+                        beat_offset: 2.0,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shift() {
+        let mut difficulty = get_difficulty();
+        difficulty.shift_beats(-1.0);
+
+        assert_eq!(difficulty.notes[0].beat, 3.0);
+        assert_eq!(difficulty.walls[0].beat, 1.0);
+        assert_eq!(difficulty.walls[0].duration, 4.0);
+        assert_eq!(difficulty.arcs[0].tail_beat, 2.0);
+
+        let event_box = &difficulty.color_event_boxes[0];
+        assert_eq!(event_box.beat, 1.0);
+        assert_eq!(event_box.groups[0].data[0].beat_offset, 2.0);
+    }
+
+    #[test]
+    fn scale() {
+        let mut difficulty = get_difficulty();
+        difficulty.scale_beats(2.0);
+
+        assert_eq!(difficulty.notes[0].beat, 8.0);
+        assert_eq!(difficulty.walls[0].duration, 8.0);
+        assert_eq!(difficulty.arcs[0].tail_beat, 6.0);
+
+        let group = &difficulty.color_event_boxes[0].groups[0];
+        assert_eq!(group.beat_dist_value, 2.0);
+        assert_eq!(group.data[0].beat_offset, 4.0);
+    }
+
+    #[test]
+    fn scale_keeps_seconds() {
+        let mut difficulty = get_difficulty();
+        difficulty.bpm_events = vec![BpmEvent {
+            beat: 3.0,
+            bpm: 60.0,
+        }];
+        let old_seconds = difficulty
+            .get_bpm_timeline(120.0)
+            .get_seconds(difficulty.notes[0].beat);
+
+        difficulty.scale_beats(2.0);
+        assert_eq!(difficulty.bpm_events[0].beat, 6.0);
+        assert_eq!(difficulty.bpm_events[0].bpm, 120.0);
+        let new_seconds = difficulty
+            .get_bpm_timeline(240.0)
+            .get_seconds(difficulty.notes[0].beat);
+        assert_eq!(new_seconds, old_seconds);
+    }
+
+    #[test]
+    #[should_panic]
+    fn scale_rejects_negative() {
+        get_difficulty().scale_beats(-1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn scale_rejects_nan() {
+        get_difficulty().scale_beats(f32::NAN);
+    }
+
+    #[test]
+    fn remap_keeps_seconds() {
+        let mut difficulty = get_difficulty();
+        let old_timeline = difficulty.get_bpm_timeline(120.0);
+        let old_seconds = old_timeline.get_seconds(difficulty.notes[0].beat);
+
+        // Doubles the BPM from beat two onwards.
+        let bpm_events = vec![BpmEvent {
+            beat: 2.0,
+            bpm: 240.0,
+        }];
+        difficulty.remap_bpm(120.0, 120.0, bpm_events.clone());
+
+        assert_eq!(difficulty.bpm_events, bpm_events);
+        let new_timeline = difficulty.get_bpm_timeline(120.0);
+        assert_eq!(
+            new_timeline.get_seconds(difficulty.notes[0].beat),
+            old_seconds
+        );
+
+        assert_eq!(difficulty.notes[0].beat, 6.0);
+        assert_eq!(difficulty.walls[0].beat, 2.0);
+        assert_eq!(difficulty.walls[0].duration, 8.0);
+        assert_eq!(difficulty.arcs[0].beat, 1.0);
+        assert_eq!(difficulty.arcs[0].tail_beat, 4.0);
+
+        let event_box = &difficulty.color_event_boxes[0];
+        assert_eq!(event_box.beat, 2.0);
+        assert_eq!(event_box.groups[0].data[0].beat_offset, 4.0);
+    }
+}
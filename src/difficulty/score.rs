@@ -0,0 +1,266 @@
+//! Calculates the maximum possible score of a difficulty, matching the game's scoring rules.
+//!
+//! Each object has a maximum cut score:
+//! - Normal notes are worth 115 points (70 for the pre-swing, 30 for the post-swing, and 15 for accuracy).
+//! - Notes attached to the head or tail of an [`Arc`](crate::Arc) are still worth 115 points,
+//!   as the arc only guarantees the post-swing (head) or pre-swing (tail).
+//! - Notes at the head of a [`Chain`](crate::Chain) are worth 85 points, as they have no post-swing.
+//! - Each [chain link](crate::Chain::links) is worth 20 points.
+//!
+//! The cut score is then multiplied by the combo multiplier, which starts at 1x and doubles up to 8x.
+//! It takes 2 objects to reach 2x, another 4 to reach 4x, and another 8 to reach 8x.
+//! Objects are scored in time order, and bombs and walls are ignored since a full combo never hits them.
+
+use crate::difficulty::Difficulty;
+use serde::Serialize;
+
+/// The maximum cut score of a normal note.
+const NOTE_SCORE: u32 = 115;
+/// The maximum cut score of a chain head, which has no post-swing.
+const CHAIN_HEAD_SCORE: u32 = 85;
+/// The score of a chain link.
+const CHAIN_LINK_SCORE: u32 = 20;
+/// Objects with a time difference less than this are considered to be at the same beat.
+const BEAT_EPSILON: f32 = 0.001;
+
+/// The kind of scoring object, which determines its maximum cut score.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum ScoreKind {
+    /// A note without an arc or chain attached.
+    Note,
+    /// A note at the head of an arc.
+    ArcHead,
+    /// A note at the tail of an arc.
+    ArcTail,
+    /// A note at the head and tail of two different arcs.
+    ArcHeadAndTail,
+    /// A note at the head of a chain.
+    ChainHead,
+    /// A link of a chain.
+    ChainLink,
+}
+
+impl ScoreKind {
+    /// Returns the maximum cut score of the object, before the combo multiplier is applied.
+    pub fn get_max_cut_score(&self) -> u32 {
+        match self {
+            ScoreKind::ChainHead => CHAIN_HEAD_SCORE,
+            ScoreKind::ChainLink => CHAIN_LINK_SCORE,
+            _ => NOTE_SCORE,
+        }
+    }
+}
+
+/// The object that produced an [`ObjectScore`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum ScoreSource {
+    /// An index into [`Difficulty::notes`].
+    Note(usize),
+    /// An index into [`Difficulty::chains`], and an index into that chain's [links](crate::Chain::links).
+    ChainLink { chain: usize, link: usize },
+}
+
+/// The maximum score of a single object.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct ObjectScore {
+    pub source: ScoreSource,
+    /// The beat of the object.
+    pub beat: f32,
+    pub kind: ScoreKind,
+    /// The combo multiplier when the object is hit, assuming a full combo.
+    pub multiplier: u32,
+    /// The maximum score of the object, including the multiplier.
+    pub score: u32,
+}
+
+impl Difficulty {
+    /// Returns the maximum possible score of the difficulty, assuming a full combo with perfect cuts.
+    ///
+    /// For details on how objects are scored, see the [module docs](crate::difficulty::score).
+    pub fn max_score(&self) -> u32 {
+        self.score_breakdown()
+            .iter()
+            .map(|object| object.score)
+            .sum()
+    }
+
+    /// Returns the maximum score of each scoring object, sorted by beat.
+    ///
+    /// For details on how objects are scored, see the [module docs](crate::difficulty::score).
+    pub fn score_breakdown(&self) -> Vec<ObjectScore> {
+        let mut objects: Vec<(ScoreSource, f32, ScoreKind)> = Vec::new();
+
+        for (i, note) in self.notes.iter().enumerate() {
+            let is_at = |beat: f32, row: i32, col: i32| {
+                (note.beat - beat).abs() < BEAT_EPSILON && note.row == row && note.col == col
+            };
+
+            let chain_head = self
+                .chains
+                .iter()
+                .any(|chain| chain.color == note.color && is_at(chain.beat, chain.row, chain.col));
+            let arc_head = self
+                .arcs
+                .iter()
+                .any(|arc| arc.color == note.color && is_at(arc.beat, arc.row, arc.col));
+            let arc_tail = self.arcs.iter().any(|arc| {
+                arc.color == note.color && is_at(arc.tail_beat, arc.tail_row, arc.tail_col)
+            });
+
+            let kind = match (chain_head, arc_head, arc_tail) {
+                (true, _, _) => ScoreKind::ChainHead,
+                (_, true, true) => ScoreKind::ArcHeadAndTail,
+                (_, true, false) => ScoreKind::ArcHead,
+                (_, false, true) => ScoreKind::ArcTail,
+                _ => ScoreKind::Note,
+            };
+            objects.push((ScoreSource::Note(i), note.beat, kind));
+        }
+
+        for (chain_index, chain) in self.chains.iter().enumerate() {
+            for (link_index, link) in chain.links().iter().enumerate() {
+                let source = ScoreSource::ChainLink {
+                    chain: chain_index,
+                    link: link_index,
+                };
+                objects.push((source, link.point.beat, ScoreKind::ChainLink));
+            }
+        }
+
+        objects.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        objects
+            .into_iter()
+            .enumerate()
+            .map(|(combo, (source, beat, kind))| {
+                let multiplier = get_multiplier(combo);
+                ObjectScore {
+                    source,
+                    beat,
+                    kind,
+                    multiplier,
+                    score: kind.get_max_cut_score() * multiplier,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the combo multiplier for an object, given the number of objects hit before it.
+fn get_multiplier(combo: usize) -> u32 {
+    match combo {
+        0..2 => 1,
+        2..6 => 2,
+        6..14 => 4,
+        _ => 8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::playfield::{Arc, Chain, Note, NoteColor};
+
+    fn get_notes(count: usize) -> Vec<Note> {
+        (0..count)
+            .map(|i| Note {
+                beat: i as f32,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn multiplier_ramp() {
+        let multipliers: Vec<u32> = (0..16).map(get_multiplier).collect();
+        assert_eq!(
+            multipliers,
+            vec![1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 4, 4, 4, 8, 8]
+        );
+    }
+
+    #[test]
+    fn notes_only() {
+        assert_eq!(Difficulty::default().max_score(), 0);
+
+        let difficulty = Difficulty {
+            notes: get_notes(1),
+            ..Default::default()
+        };
+        assert_eq!(difficulty.max_score(), 115);
+
+        // 2 at 1x, 4 at 2x, 8 at 4x, 1 at 8x.
+        let difficulty = Difficulty {
+            notes: get_notes(15),
+            ..Default::default()
+        };
+        assert_eq!(difficulty.max_score(), 115 * (2 + 4 * 2 + 8 * 4 + 8));
+    }
+
+    #[test]
+    fn chains() {
+        let difficulty = Difficulty {
+            notes: vec![Note {
+                beat: 0.0,
+                row: 1,
+                col: 1,
+                color: NoteColor::Right,
+                ..Default::default()
+            }],
+            chains: vec![Chain {
+                beat: 0.0,
+                row: 1,
+                col: 1,
+                color: NoteColor::Right,
+                tail_beat: 0.5,
+                link_count: 3,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let breakdown = difficulty.score_breakdown();
+        assert_eq!(breakdown.len(), 3);
+        assert_eq!(breakdown[0].kind, ScoreKind::ChainHead);
+        assert_eq!(
+            breakdown[2].source,
+            ScoreSource::ChainLink { chain: 0, link: 1 }
+        );
+        assert_eq!(breakdown[2].multiplier, 2);
+        assert_eq!(difficulty.max_score(), 85 + 20 + 20 * 2);
+    }
+
+    #[test]
+    fn arcs_keep_full_score() {
+        let difficulty = Difficulty {
+            notes: get_notes(2),
+            arcs: vec![Arc {
+                beat: 0.0,
+                tail_beat: 1.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let breakdown = difficulty.score_breakdown();
+        assert_eq!(breakdown[0].kind, ScoreKind::ArcHead);
+        assert_eq!(breakdown[1].kind, ScoreKind::ArcTail);
+        assert_eq!(difficulty.max_score(), 230);
+    }
+}
@@ -0,0 +1,440 @@
+//! Extracts sections of a difficulty, and splices sections into other difficulties.
+//!
+//! A section is a normal [`Difficulty`], with its beats relative to the start of the section.
+//! When slicing, objects are included if they start within the range (start inclusive, end exclusive).
+//! Walls and arcs that overlap the range are also included, and are clipped to fit inside of it.
+//! Chains that end after the range are removed, since their links can't be clipped.
+//!
+//! The BPM and lane rotation at the start of the range are kept, by adding a [`BpmEvent`] and [`LaneRotationEvent`]
+//! at the start of the section (if needed). Relative beats inside of event boxes are unchanged.
+//!
+//! When splicing, the section's BPM and lane rotation events (including V2 rotation events) are ignored,
+//! so that the timing and rotation of the rest of the difficulty are unchanged.
+//! Spliced objects are placed after any existing objects on the same beat, and existing objects are never reordered.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::gameplay_event::{BpmEvent, ExecutionTime, LaneRotationEvent};
+use crate::difficulty::playfield::{Arc, CutDirection};
+use crate::spatial::{BASE_LAYER_Y, CurvePoint, LANE_WIDTH, LAYER_HEIGHT};
+use crate::timing_traits::{Duration, Timed};
+use std::ops::Range;
+
+impl Difficulty {
+    /// Returns a section of the difficulty, with beats relative to the start of the range.
+    ///
+    /// For details, see the [module docs](crate::difficulty::slice).
+    pub fn slice(&self, range: Range<f32>) -> Self {
+        let mut section = self.clone();
+
+        retain_in_range(&mut section.bpm_events, &range);
+        if let Some(event) = self
+            .bpm_events
+            .iter()
+            .rfind(|event| event.beat < range.start)
+            && section
+                .bpm_events
+                .first()
+                .is_none_or(|first| first.beat > range.start)
+        {
+            section.bpm_events.insert(
+                0,
+                BpmEvent {
+                    beat: range.start,
+                    bpm: event.bpm,
+                },
+            );
+        }
+
+        retain_in_range(&mut section.lane_rotation_events, &range);
+        // Early events on the first beat (including V2 rotation events) are kept, so they shouldn't be counted twice.
+        let basic_rotations = (section.basic_events.iter())
+            .filter(|event| event.beat == range.start)
+            .filter_map(LaneRotationEvent::from_basic_event);
+        let rotation = self.get_lane_rotation_timeline().get_rotation(range.start)
+            - (section.lane_rotation_events.iter().cloned())
+                .chain(basic_rotations)
+                .filter(|event| {
+                    event.beat == range.start && event.execution_time == ExecutionTime::Early
+                })
+                .map(|event| event.degrees)
+                .sum::<f32>();
+        if rotation != 0.0 {
+            section.lane_rotation_events.insert(
+                0,
+                LaneRotationEvent {
+                    beat: range.start,
+                    execution_time: ExecutionTime::Early,
+                    degrees: rotation,
+                },
+            );
+        }
+
+        retain_in_range(&mut section.notes, &range);
+        retain_in_range(&mut section.bombs, &range);
+        section
+            .chains
+            .retain(|chain| range.contains(&chain.beat) && chain.tail_beat <= range.end);
+        retain_in_range(&mut section.waypoints, &range);
+        retain_in_range(&mut section.basic_events, &range);
+        retain_in_range(&mut section.color_boost_events, &range);
+        retain_in_range(&mut section.color_event_boxes, &range);
+        retain_in_range(&mut section.rotation_event_boxes, &range);
+        if let Some(container) = &mut section.fx_event_boxes {
+            retain_in_range(&mut container.event_boxes, &range);
+        }
+        if let Some(event_boxes) = &mut section.translation_event_boxes {
+            retain_in_range(event_boxes, &range);
+        }
+
+        section.walls.retain(|wall| overlaps(wall, &range));
+        for wall in &mut section.walls {
+            let beat = wall.beat.max(range.start);
+            wall.duration = wall.get_end_beat().min(range.end) - beat;
+            wall.beat = beat;
+        }
+        section.arcs = (self.arcs.iter())
+            .filter(|arc| overlaps(*arc, &range))
+            .map(|arc| clip_arc(arc, &range))
+            .collect();
+
+        section.shift_beats(-range.start);
+        section
+    }
+
+    /// Inserts a section into the difficulty at a beat, keeping every list sorted by beat.
+    ///
+    /// Existing objects are not removed. The section's BPM and lane rotation events (including V2 rotation events)
+    /// are not inserted, as they would also change the timing and rotation of every following object.
+    /// Instead, the section uses the BPM and rotation of the difficulty at the beat it is inserted.
+    pub fn splice(&mut self, beat: f32, section: &Difficulty) {
+        let mut section = section.clone();
+        section.shift_beats(beat);
+        splice_sorted(&mut self.notes, section.notes);
+        splice_sorted(&mut self.bombs, section.bombs);
+        splice_sorted(&mut self.walls, section.walls);
+        splice_sorted(&mut self.arcs, section.arcs);
+        splice_sorted(&mut self.chains, section.chains);
+        splice_sorted(&mut self.waypoints, section.waypoints);
+        section
+            .basic_events
+            .retain(|event| LaneRotationEvent::from_basic_event(event).is_none());
+        splice_sorted(&mut self.basic_events, section.basic_events);
+        splice_sorted(&mut self.color_boost_events, section.color_boost_events);
+        splice_sorted(&mut self.color_event_boxes, section.color_event_boxes);
+        splice_sorted(&mut self.rotation_event_boxes, section.rotation_event_boxes);
+
+        if let Some(container) = section.fx_event_boxes {
+            let event_boxes = &mut self.fx_event_boxes.get_or_insert_default().event_boxes;
+            splice_sorted(event_boxes, container.event_boxes);
+        }
+        if let Some(section_boxes) = section.translation_event_boxes {
+            let event_boxes = self.translation_event_boxes.get_or_insert_default();
+            splice_sorted(event_boxes, section_boxes);
+        }
+    }
+}
+
+fn retain_in_range<T: Timed>(objects: &mut Vec<T>, range: &Range<f32>) {
+    objects.retain(|object| range.contains(&object.get_beat()));
+}
+
+fn overlaps(object: &impl Duration, range: &Range<f32>) -> bool {
+    range.contains(&object.get_beat())
+        || object.get_beat() < range.end && object.get_end_beat() > range.start
+}
+
+/// Adds objects to a list, keeping it sorted by beat (and keeping the order of objects on the same beat).
+pub(crate) fn splice_sorted<T: Timed>(objects: &mut Vec<T>, section: Vec<T>) {
+    splice_sorted_by(objects, section, T::get_beat);
+}
+
+/// Merges objects into a list after sorting them by beat, placing each one after any existing objects on the same beat.
+///
+/// The existing objects keep their order, even if they weren't sorted.
+pub(crate) fn splice_sorted_by<T>(
+    objects: &mut Vec<T>,
+    mut section: Vec<T>,
+    get_beat: impl Fn(&T) -> f32,
+) {
+    section.sort_by(|a, b| get_beat(a).total_cmp(&get_beat(b)));
+
+    let mut merged = Vec::with_capacity(objects.len() + section.len());
+    let mut section = section.into_iter().peekable();
+    for object in objects.drain(..) {
+        while let Some(next) = section.next_if(|next| get_beat(next) < get_beat(&object)) {
+            merged.push(next);
+        }
+        merged.push(object);
+    }
+    merged.extend(section);
+    *objects = merged;
+}
+
+/// Shortens an arc so that it fits inside of a range.
+///
+/// A clipped end is moved to the nearest grid cell along the curve, and can be cut in any direction.
+fn clip_arc(arc: &Arc, range: &Range<f32>) -> Arc {
+    let duration = arc.tail_beat - arc.beat;
+    if duration <= 0.0 {
+        return arc.clone();
+    }
+
+    let mut clipped = arc.clone();
+    if arc.beat < range.start {
+        let point = arc.sample((range.start - arc.beat) / duration);
+        clipped.beat = point.beat;
+        (clipped.col, clipped.row) = get_nearest_cell(point);
+        clipped.direction = CutDirection::Any;
+    }
+    if arc.tail_beat > range.end {
+        let point = arc.sample((range.end - arc.beat) / duration);
+        clipped.tail_beat = point.beat;
+        (clipped.tail_col, clipped.tail_row) = get_nearest_cell(point);
+        clipped.tail_direction = CutDirection::Any;
+    }
+
+    clipped
+}
+
+/// Returns the nearest cell of the 4x3 grid to a point.
+fn get_nearest_cell(point: CurvePoint) -> (i32, i32) {
+    let col = (point.x / LANE_WIDTH + 1.5).round() as i32;
+    let row = ((point.y - BASE_LAYER_Y) / LAYER_HEIGHT).round() as i32;
+    (col.clamp(0, 3), row.clamp(0, 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::lane_rotation::EARLY_ROTATION_EVENT_TYPE;
+    use crate::difficulty::lightshow::BasicEvent;
+    use crate::difficulty::playfield::{Chain, Note, Wall};
+
+    fn get_note(beat: f32) -> Note {
+        Note {
+            beat,
+            ..Default::default()
+        }
+    }
+
+    fn get_beats(difficulty: &Difficulty) -> Vec<f32> {
+        difficulty.notes.iter().map(|note| note.beat).collect()
+    }
+
+    #[test]
+    fn slice_notes() {
+        let difficulty = Difficulty {
+            notes: (0..8).map(|i| get_note(i as f32)).collect(),
+            ..Default::default()
+        };
+
+        let section = difficulty.slice(2.0..5.0);
+        assert_eq!(get_beats(&section), vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn slice_clips_walls_and_arcs() {
+        let difficulty = Difficulty {
+            walls: vec![
+                Wall {
+                    beat: 1.0,
+                    duration: 2.0,
+                    ..Default::default()
+                },
+                Wall {
+                    beat: 0.0,
+                    duration: 1.0,
+                    ..Default::default()
+                },
+            ],
+            arcs: vec![Arc {
+                beat: 0.0,
+                row: 0,
+                col: 0,
+                direction: CutDirection::Any,
+                tail_beat: 4.0,
+                tail_row: 0,
+                tail_col: 3,
+                tail_direction: CutDirection::Any,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let section = difficulty.slice(2.0..3.0);
+        assert_eq!(section.walls.len(), 1);
+        assert_eq!(section.walls[0].beat, 0.0);
+        assert_eq!(section.walls[0].duration, 1.0);
+
+        let arc = &section.arcs[0];
+        assert_eq!((arc.beat, arc.tail_beat), (0.0, 1.0));
+        assert_eq!((arc.col, arc.tail_col), (2, 3));
+        assert_eq!(arc.direction, CutDirection::Any);
+    }
+
+    #[test]
+    fn slice_keeps_bpm_and_rotation() {
+        let difficulty = Difficulty {
+            bpm_events: vec![BpmEvent {
+                beat: 1.0,
+                bpm: 200.0,
+            }],
+            lane_rotation_events: vec![LaneRotationEvent {
+                beat: 1.0,
+                execution_time: ExecutionTime::Early,
+                degrees: 30.0,
+            }],
+            ..Default::default()
+        };
+
+        let section = difficulty.slice(4.0..8.0);
+        assert_eq!(
+            section.bpm_events,
+            vec![BpmEvent {
+                beat: 0.0,
+                bpm: 200.0
+            }]
+        );
+        assert_eq!(section.get_lane_rotation_timeline().get_rotation(0.0), 30.0);
+    }
+
+    #[test]
+    fn slice_keeps_v2_rotation_once() {
+        let difficulty = Difficulty {
+            basic_events: vec![BasicEvent {
+                beat: 4.0,
+                event_type: EARLY_ROTATION_EVENT_TYPE,
+                value: 5,
+                float: 0.0,
+            }],
+            ..Default::default()
+        };
+
+        let section = difficulty.slice(4.0..8.0);
+        assert!(section.lane_rotation_events.is_empty());
+        assert_eq!(section.get_lane_rotation_timeline().get_rotation(0.0), 30.0);
+    }
+
+    #[test]
+    fn clipped_arc_stays_on_grid() {
+        let difficulty = Difficulty {
+            arcs: vec![Arc {
+                beat: 0.0,
+                row: 0,
+                col: 0,
+                direction: CutDirection::Down,
+                control_point: 10.0,
+                tail_beat: 4.0,
+                tail_row: 0,
+                tail_col: 3,
+                tail_direction: CutDirection::Down,
+                tail_control_point: 10.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let arc = &difficulty.slice(2.0..3.0).arcs[0];
+        assert!((0..=3).contains(&arc.col) && (0..=2).contains(&arc.row));
+        assert!((0..=3).contains(&arc.tail_col) && (0..=2).contains(&arc.tail_row));
+    }
+
+    #[test]
+    fn splice_ignores_section_timing() {
+        let mut difficulty = Difficulty {
+            notes: vec![get_note(8.0)],
+            ..Default::default()
+        };
+        let section = Difficulty {
+            bpm_events: vec![BpmEvent {
+                beat: 0.0,
+                bpm: 200.0,
+            }],
+            lane_rotation_events: vec![LaneRotationEvent {
+                beat: 0.0,
+                execution_time: ExecutionTime::Early,
+                degrees: 30.0,
+            }],
+            notes: vec![get_note(0.0)],
+            ..Default::default()
+        };
+
+        difficulty.splice(4.0, &section);
+        assert_eq!(get_beats(&difficulty), vec![4.0, 8.0]);
+        assert!(difficulty.bpm_events.is_empty());
+        assert!(difficulty.lane_rotation_events.is_empty());
+    }
+
+    #[test]
+    fn slice_removes_unfinished_chains() {
+        let get_chain = |beat, tail_beat| Chain {
+            beat,
+            tail_beat,
+            ..Default::default()
+        };
+        let difficulty = Difficulty {
+            chains: vec![get_chain(1.0, 10.0), get_chain(1.0, 2.0)],
+            ..Default::default()
+        };
+
+        let section = difficulty.slice(0.0..2.0);
+        assert_eq!(section.chains, vec![get_chain(1.0, 2.0)]);
+    }
+
+    #[test]
+    fn splice_ignores_v2_rotation() {
+        let source = Difficulty {
+            notes: vec![get_note(5.0)],
+            basic_events: vec![BasicEvent {
+                beat: 4.0,
+                event_type: EARLY_ROTATION_EVENT_TYPE,
+                value: 5,
+                float: 0.0,
+            }],
+            ..Default::default()
+        };
+        let mut difficulty = Difficulty::default();
+
+        difficulty.splice(10.0, &source.slice(4.0..8.0));
+        assert_eq!(get_beats(&difficulty), vec![11.0]);
+        assert!(difficulty.basic_events.is_empty());
+        assert_eq!(
+            difficulty.get_lane_rotation_timeline().get_rotation(20.0),
+            0.0
+        );
+    }
+
+    #[test]
+    fn splice_keeps_existing_order() {
+        let mut difficulty = Difficulty {
+            notes: vec![get_note(2.0), get_note(1.0), get_note(4.0), get_note(8.0)],
+            ..Default::default()
+        };
+        let section = Difficulty {
+            notes: vec![get_note(1.0), get_note(0.0)],
+            ..Default::default()
+        };
+
+        // The existing notes weren't sorted, but still keep their order.
+        difficulty.splice(4.0, &section);
+        assert_eq!(get_beats(&difficulty), vec![2.0, 1.0, 4.0, 4.0, 5.0, 8.0]);
+    }
+
+    #[test]
+    fn splice_section() {
+        let mut difficulty = Difficulty {
+            notes: vec![get_note(0.0), get_note(8.0)],
+            ..Default::default()
+        };
+        let section = Difficulty {
+            notes: vec![get_note(0.0), get_note(1.0)],
+            ..Default::default()
+        };
+
+        difficulty.splice(4.0, &section);
+        assert_eq!(get_beats(&difficulty), vec![0.0, 4.0, 5.0, 8.0]);
+
+        let round_trip = difficulty.slice(4.0..6.0);
+        assert_eq!(round_trip.notes, section.notes);
+    }
+}
@@ -0,0 +1,214 @@
+//! Snaps object beats to musical subdivisions, and finds objects that have drifted off of them.
+//!
+//! Beats are stored as `f32`, so saving and loading a map in different editors can slowly add floating-point noise.
+//! Subdivisions are measured from the start of the [BPM region](crate::difficulty::bpm::BpmRegion) that an object is in,
+//! so that objects stay snapped after a BPM change that isn't on a whole beat.
+
+use crate::difficulty::bpm::BpmTimeline;
+use crate::difficulty::{Difficulty, ObjectRef};
+use crate::timing_traits::Timed;
+use serde::Serialize;
+
+/// Controls which subdivisions objects are [snapped](Difficulty::snap_beats) to.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct SnapOptions {
+    /// The allowed subdivisions of a beat, i.e. `4` for quarter beats and `3` for triplets.
+    ///
+    /// When two subdivisions are equally close, the earlier one is used.
+    pub divisions: Vec<u32>,
+    /// The largest deviation (in beats) that is considered floating-point noise, rather than an intentionally unsnapped object.
+    pub tolerance: f32,
+}
+
+impl Default for SnapOptions {
+    fn default() -> Self {
+        Self {
+            divisions: vec![4, 3, 8, 12, 16],
+            tolerance: 0.001,
+        }
+    }
+}
+
+impl SnapOptions {
+    /// Returns the nearest allowed subdivision to a beat, relative to the start of its BPM region.
+    ///
+    /// Returns the beat unchanged if there are no [divisions](Self::divisions).
+    pub fn snap(&self, timeline: &BpmTimeline, beat: f32) -> f32 {
+        let start = timeline.get_region(beat).beat;
+        let offset = beat - start;
+
+        let snapped = self
+            .divisions
+            .iter()
+            .filter(|division| **division > 0)
+            .map(|division| {
+                let division = *division as f32;
+                (offset * division).round() / division
+            })
+            .reduce(|best, snapped| {
+                if (snapped - offset).abs() < (best - offset).abs() {
+                    snapped
+                } else {
+                    best
+                }
+            });
+
+        match snapped {
+            Some(snapped) => start + snapped,
+            None => beat,
+        }
+    }
+}
+
+/// A beat that is slightly off of the nearest subdivision, found by [`Difficulty::audit_precision`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct PrecisionIssue {
+    /// The object that is off-grid. Objects with an end beat (i.e. walls) may be reported twice.
+    pub object: ObjectRef,
+    /// The current beat.
+    pub beat: f32,
+    /// The nearest subdivision.
+    pub snapped_beat: f32,
+    /// The difference between the current beat and the nearest subdivision.
+    pub deviation: f32,
+}
+
+impl Difficulty {
+    /// Snaps the beat of every object to the nearest allowed subdivision, including objects that are intentionally unsnapped.
+    ///
+    /// The base BPM is defined by the map's [info file](crate::info::Beatmap::bpm).
+    pub fn snap_beats(&mut self, base_bpm: f32, options: &SnapOptions) {
+        let timeline = self.get_bpm_timeline(base_bpm);
+        self.map_beats(|_, beat| options.snap(&timeline, beat));
+    }
+
+    /// Snaps only the beats that are within the [tolerance](SnapOptions::tolerance) of a subdivision,
+    /// removing floating-point noise without moving intentionally unsnapped objects.
+    ///
+    /// The base BPM is defined by the map's [info file](crate::info::Beatmap::bpm).
+    pub fn fix_precision(&mut self, base_bpm: f32, options: &SnapOptions) {
+        let timeline = self.get_bpm_timeline(base_bpm);
+        self.map_beats(|_, beat| {
+            let snapped = options.snap(&timeline, beat);
+            if (beat - snapped).abs() <= options.tolerance {
+                snapped
+            } else {
+                beat
+            }
+        });
+    }
+
+    /// Returns every beat that is off of the nearest subdivision by a non-zero amount within the [tolerance](SnapOptions::tolerance).
+    ///
+    /// The base BPM is defined by the map's [info file](crate::info::Beatmap::bpm).
+    pub fn audit_precision(&self, base_bpm: f32, options: &SnapOptions) -> Vec<PrecisionIssue> {
+        let timeline = self.get_bpm_timeline(base_bpm);
+
+        // The end beats of walls, arcs and chains are checked along with their start beats.
+        (self.iter_timed())
+            .flat_map(|(object, timed)| {
+                let end_beat = timed.as_duration().map(|duration| duration.get_end_beat());
+                [Some(timed.get_beat()), end_beat]
+                    .into_iter()
+                    .flatten()
+                    .map(move |beat| (object, beat))
+            })
+            .filter_map(|(object, beat)| {
+                let snapped_beat = options.snap(&timeline, beat);
+                let deviation = beat - snapped_beat;
+                (deviation != 0.0 && deviation.abs() <= options.tolerance).then_some(
+                    PrecisionIssue {
+                        object,
+                        beat,
+                        snapped_beat,
+                        deviation,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::gameplay_event::BpmEvent;
+    use crate::difficulty::playfield::{Note, Wall};
+
+    fn get_note(beat: f32) -> Note {
+        Note {
+            beat,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn nearest_division() {
+        let timeline = BpmTimeline::new(120.0, &[]);
+        let options = SnapOptions::default();
+
+        assert_eq!(options.snap(&timeline, 1.26), 1.25);
+        assert_eq!(options.snap(&timeline, 1.33), 1.0 + 1.0 / 3.0);
+        assert_eq!(options.snap(&timeline, 1.126), 1.125);
+
+        let options = SnapOptions {
+            divisions: vec![],
+            ..Default::default()
+        };
+        assert_eq!(options.snap(&timeline, 1.26), 1.26);
+    }
+
+    #[test]
+    fn relative_to_bpm_region() {
+        let timeline = BpmTimeline::new(
+            120.0,
+            &[BpmEvent {
+                beat: 10.1,
+                bpm: 60.0,
+            }],
+        );
+        let options = SnapOptions::default();
+
+        assert_eq!(options.snap(&timeline, 10.0), 10.0);
+        assert_eq!(options.snap(&timeline, 10.6), 10.6);
+    }
+
+    #[test]
+    fn audit_and_fix() {
+        let mut difficulty = Difficulty {
+            notes: vec![get_note(2.0), get_note(2.5002), get_note(3.12)],
+            walls: vec![Wall {
+                beat: 4.0,
+                duration: 0.9999,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let options = SnapOptions::default();
+
+        let issues = difficulty.audit_precision(120.0, &options);
+        let objects: Vec<ObjectRef> = issues.iter().map(|issue| issue.object).collect();
+        assert_eq!(objects, vec![ObjectRef::Note(1), ObjectRef::Wall(0)]);
+        assert_eq!(issues[0].snapped_beat, 2.5);
+
+        difficulty.fix_precision(120.0, &options);
+        assert!(difficulty.audit_precision(120.0, &options).is_empty());
+        assert_eq!(difficulty.notes[1].beat, 2.5);
+        assert_eq!(difficulty.notes[2].beat, 3.12);
+        assert_eq!(difficulty.walls[0].duration, 1.0);
+
+        difficulty.snap_beats(120.0, &options);
+        assert_eq!(difficulty.notes[2].beat, 3.125);
+    }
+}
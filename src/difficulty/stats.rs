@@ -0,0 +1,303 @@
+//! Note density statistics, such as notes per second (NPS).
+//!
+//! Every [`Note`](crate::Note) is counted, as well as each [link](crate::Chain::links) of a [`Chain`](crate::Chain).
+//! Bombs and arcs are not counted, as they do not need to be cut.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::playfield::NoteColor;
+use serde::Serialize;
+
+/// Controls how [note statistics](Difficulty::get_note_stats) are calculated.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct NoteStatsOptions {
+    /// The length of the sliding window (in seconds) used to find the [peak NPS](NoteStats::peak).
+    pub peak_window: f32,
+    /// The length of each [section](NoteStats::sections), in seconds.
+    pub section_length: f32,
+}
+
+impl Default for NoteStatsOptions {
+    fn default() -> Self {
+        Self {
+            peak_window: 2.0,
+            section_length: 4.0,
+        }
+    }
+}
+
+/// Note density statistics for a difficulty, with all times in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct NoteStats {
+    /// The total number of notes, including chain links.
+    pub note_count: usize,
+    /// The number of notes that are cut by the left saber.
+    pub left_count: usize,
+    /// The number of notes that are cut by the right saber.
+    pub right_count: usize,
+    /// The time of the first note.
+    pub start_seconds: f32,
+    /// The time of the last note.
+    pub end_seconds: f32,
+    /// The average notes per second, from the first to the last note.
+    pub nps: f32,
+    /// The densest section of the difficulty, or `None` if there are no notes.
+    pub peak: Option<DensitySection>,
+    /// The density of each consecutive section, from the start of the song to the last note.
+    pub sections: Vec<DensitySection>,
+}
+
+impl NoteStats {
+    /// Returns the fraction of hand-specific notes that are cut by the left saber, or `0.5` if there are none.
+    pub fn get_left_ratio(&self) -> f32 {
+        let total = self.left_count + self.right_count;
+        if total == 0 {
+            0.5
+        } else {
+            self.left_count as f32 / total as f32
+        }
+    }
+}
+
+/// The note density of a section of the song.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct DensitySection {
+    /// The start of the section, in seconds.
+    pub start_seconds: f32,
+    /// The end of the section (exclusive), in seconds.
+    pub end_seconds: f32,
+    /// The number of notes in the section, including chain links.
+    pub note_count: usize,
+    /// The notes per second within the section.
+    pub nps: f32,
+}
+
+impl Difficulty {
+    /// Returns note density statistics, such as notes per second (NPS).
+    ///
+    /// The base BPM is defined by the map's [info file](crate::info::Beatmap::bpm).
+    /// For details on what counts as a note, see the [module docs](crate::difficulty::stats).
+    pub fn get_note_stats(&self, base_bpm: f32, options: &NoteStatsOptions) -> NoteStats {
+        let timeline = self.get_bpm_timeline(base_bpm);
+
+        let mut notes: Vec<(f32, NoteColor)> = self
+            .notes
+            .iter()
+            .map(|note| (note.beat, note.color))
+            .collect();
+        for chain in &self.chains {
+            notes.extend(
+                chain
+                    .links()
+                    .iter()
+                    .map(|link| (link.point.beat, chain.color)),
+            );
+        }
+
+        let mut times: Vec<f32> = notes
+            .iter()
+            .map(|(beat, _)| timeline.get_seconds(*beat))
+            .collect();
+        times.sort_by(f32::total_cmp);
+
+        let count_color = |color: NoteColor| notes.iter().filter(|(_, c)| *c == color).count();
+
+        let start_seconds = times.first().copied().unwrap_or(0.0);
+        let end_seconds = times.last().copied().unwrap_or(0.0);
+        let duration = end_seconds - start_seconds;
+
+        NoteStats {
+            note_count: times.len(),
+            left_count: count_color(NoteColor::Left),
+            right_count: count_color(NoteColor::Right),
+            start_seconds,
+            end_seconds,
+            nps: if duration > 0.0 {
+                times.len() as f32 / duration
+            } else {
+                0.0
+            },
+            peak: get_peak(&times, options.peak_window),
+            sections: get_sections(&times, options.section_length),
+        }
+    }
+}
+
+/// Finds the sliding window with the most notes, given sorted note times.
+fn get_peak(times: &[f32], window: f32) -> Option<DensitySection> {
+    if window <= 0.0 {
+        return None;
+    }
+
+    let mut peak: Option<DensitySection> = None;
+    let mut end = 0;
+
+    for (start, time) in times.iter().enumerate() {
+        while end < times.len() && times[end] < time + window {
+            end += 1;
+        }
+
+        let count = end - start;
+        if peak.as_ref().is_none_or(|peak| count > peak.note_count) {
+            peak = Some(DensitySection {
+                start_seconds: *time,
+                end_seconds: time + window,
+                note_count: count,
+                nps: count as f32 / window,
+            });
+        }
+    }
+
+    peak
+}
+
+/// Splits the song into sections of equal length, given sorted note times.
+fn get_sections(times: &[f32], length: f32) -> Vec<DensitySection> {
+    let Some(last) = times.last() else {
+        return Vec::new();
+    };
+    if length <= 0.0 {
+        return Vec::new();
+    }
+
+    let section_count = (last / length).floor().max(0.0) as usize + 1;
+    let mut sections: Vec<DensitySection> = (0..section_count)
+        .map(|i| DensitySection {
+            start_seconds: i as f32 * length,
+            end_seconds: (i + 1) as f32 * length,
+            note_count: 0,
+            nps: 0.0,
+        })
+        .collect();
+
+    for time in times {
+        let index = (time / length).floor().max(0.0) as usize;
+        sections[index.min(section_count - 1)].note_count += 1;
+    }
+
+    for section in &mut sections {
+        section.nps = section.note_count as f32 / length;
+    }
+
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::gameplay_event::BpmEvent;
+    use crate::difficulty::playfield::{Bomb, Chain, Note};
+
+    fn get_note(beat: f32, color: NoteColor) -> Note {
+        Note {
+            beat,
+            color,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let stats = Difficulty::default().get_note_stats(120.0, &NoteStatsOptions::default());
+
+        assert_eq!(stats.note_count, 0);
+        assert_eq!(stats.nps, 0.0);
+        assert_eq!(stats.peak, None);
+        assert!(stats.sections.is_empty());
+        assert_eq!(stats.get_left_ratio(), 0.5);
+    }
+
+    #[test]
+    fn counts_chain_links_and_ignores_bombs() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Left),
+                get_note(4.0, NoteColor::Right),
+            ],
+            chains: vec![Chain {
+                beat: 0.0,
+                tail_beat: 1.0,
+                link_count: 5,
+                color: NoteColor::Left,
+                ..Default::default()
+            }],
+            bombs: vec![Bomb::default()],
+            ..Default::default()
+        };
+
+        let stats = difficulty.get_note_stats(60.0, &NoteStatsOptions::default());
+
+        assert_eq!(stats.note_count, 6);
+        assert_eq!(stats.left_count, 5);
+        assert_eq!(stats.right_count, 1);
+        assert_eq!(stats.nps, 1.5);
+        assert_eq!(stats.get_left_ratio(), 5.0 / 6.0);
+    }
+
+    #[test]
+    fn uses_bpm_changes() {
+        let difficulty = Difficulty {
+            notes: (0..=8)
+                .map(|i| get_note(i as f32, NoteColor::Left))
+                .collect(),
+            bpm_events: vec![BpmEvent {
+                beat: 4.0,
+                bpm: 240.0,
+            }],
+            ..Default::default()
+        };
+
+        let stats = difficulty.get_note_stats(60.0, &NoteStatsOptions::default());
+
+        // Four beats at 60 BPM, then four at 240 BPM.
+        assert_eq!(stats.end_seconds, 5.0);
+        assert_eq!(stats.nps, 9.0 / 5.0);
+
+        let peak = stats.peak.unwrap();
+        assert_eq!(peak.start_seconds, 3.0);
+        assert_eq!(peak.note_count, 5);
+        assert_eq!(peak.nps, 2.5);
+    }
+
+    #[test]
+    fn sections() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, NoteColor::Left),
+                get_note(1.0, NoteColor::Left),
+                get_note(9.0, NoteColor::Right),
+            ],
+            ..Default::default()
+        };
+
+        let options = NoteStatsOptions {
+            section_length: 4.0,
+            ..Default::default()
+        };
+        let stats = difficulty.get_note_stats(60.0, &options);
+
+        let counts: Vec<usize> = stats
+            .sections
+            .iter()
+            .map(|section| section.note_count)
+            .collect();
+        assert_eq!(counts, vec![2, 0, 1]);
+        assert_eq!(stats.sections[0].nps, 0.5);
+    }
+}
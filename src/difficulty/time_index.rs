@@ -0,0 +1,341 @@
+//! An index for quickly finding objects by beat, without scanning every list in a difficulty.
+//!
+//! Objects with a [`Duration`] (walls, arcs and chains) are stored as intervals,
+//! and are found by any range that they overlap. Every other object (including event boxes) is stored as a single beat.
+//!
+//! Intervals are stored in an implicit interval tree, so long objects (such as a wall that lasts the entire song)
+//! do not slow down queries.
+//!
+//! The index stores [references](ObjectRef) rather than copies of the objects,
+//! so it must be rebuilt after the difficulty is changed.
+
+use crate::difficulty::playfield::NoteColor;
+use crate::difficulty::{Difficulty, ObjectRef};
+use crate::timing_traits::{Duration, Timed};
+use std::ops::Range;
+
+/// An index of every object in a difficulty, sorted by beat. Created using [`Difficulty::get_time_index`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct TimeIndex {
+    /// Objects without a duration, sorted by beat.
+    points: Vec<IndexEntry>,
+    /// Objects with a duration, sorted by start beat.
+    ///
+    /// This is treated as a balanced binary tree, where each sub-slice's root is its middle entry.
+    intervals: Vec<IndexEntry>,
+    /// The latest end beat of the sub-tree rooted at each interval.
+    max_end_beats: Vec<f32>,
+    /// The notes of each color, sorted by beat.
+    notes: Vec<(NoteColor, Vec<IndexEntry>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+struct IndexEntry {
+    beat: f32,
+    end_beat: f32,
+    object: ObjectRef,
+}
+
+impl TimeIndex {
+    pub fn new(difficulty: &Difficulty) -> Self {
+        let mut points = Vec::new();
+        add_points(&mut points, &difficulty.bpm_events, ObjectRef::BpmEvent);
+        add_points(
+            &mut points,
+            &difficulty.lane_rotation_events,
+            ObjectRef::LaneRotationEvent,
+        );
+        add_points(&mut points, &difficulty.notes, ObjectRef::Note);
+        add_points(&mut points, &difficulty.bombs, ObjectRef::Bomb);
+        add_points(&mut points, &difficulty.waypoints, ObjectRef::Waypoint);
+        add_points(&mut points, &difficulty.basic_events, ObjectRef::BasicEvent);
+        add_points(
+            &mut points,
+            &difficulty.color_boost_events,
+            ObjectRef::ColorBoostEvent,
+        );
+        add_points(
+            &mut points,
+            &difficulty.color_event_boxes,
+            ObjectRef::ColorEventBox,
+        );
+        add_points(
+            &mut points,
+            &difficulty.rotation_event_boxes,
+            ObjectRef::RotationEventBox,
+        );
+        if let Some(container) = &difficulty.fx_event_boxes {
+            add_points(&mut points, container, ObjectRef::FxEventBox);
+        }
+        if let Some(event_boxes) = &difficulty.translation_event_boxes {
+            add_points(&mut points, event_boxes, ObjectRef::TranslationEventBox);
+        }
+
+        let mut intervals = Vec::new();
+        add_intervals(&mut intervals, &difficulty.walls, ObjectRef::Wall);
+        add_intervals(&mut intervals, &difficulty.arcs, ObjectRef::Arc);
+        add_intervals(&mut intervals, &difficulty.chains, ObjectRef::Chain);
+
+        let mut notes: Vec<(NoteColor, Vec<IndexEntry>)> = Vec::new();
+        for (i, note) in difficulty.notes.iter().enumerate() {
+            let entry = IndexEntry {
+                beat: note.beat,
+                end_beat: note.beat,
+                object: ObjectRef::Note(i),
+            };
+            match notes.iter_mut().find(|(color, _)| *color == note.color) {
+                Some((_, entries)) => entries.push(entry),
+                None => notes.push((note.color, vec![entry])),
+            }
+        }
+
+        sort_entries(&mut points);
+        sort_entries(&mut intervals);
+        let mut max_end_beats = vec![f32::NEG_INFINITY; intervals.len()];
+        build_max_end_beats(&intervals, &mut max_end_beats, 0..intervals.len());
+        for (_, entries) in &mut notes {
+            sort_entries(entries);
+        }
+
+        Self {
+            points,
+            intervals,
+            max_end_beats,
+            notes,
+        }
+    }
+
+    /// Returns the total number of objects in the index.
+    pub fn len(&self) -> usize {
+        self.points.len() + self.intervals.len()
+    }
+
+    /// Returns true if the index contains no objects.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns every object that takes place within a range (start inclusive, end exclusive), sorted by beat.
+    ///
+    /// Walls, arcs and chains are included if they overlap the range, even if they start before it.
+    pub fn get_objects_in(&self, range: Range<f32>) -> Vec<ObjectRef> {
+        let mut entries: Vec<&IndexEntry> = get_starting_in(&self.points, &range).iter().collect();
+
+        self.find_intervals(0..self.intervals.len(), &range, &mut entries);
+
+        entries.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        entries.into_iter().map(|entry| entry.object).collect()
+    }
+
+    /// Adds every interval in a sub-tree that overlaps a range, skipping sub-trees that can't overlap it.
+    fn find_intervals<'a>(
+        &'a self,
+        indices: Range<usize>,
+        range: &Range<f32>,
+        entries: &mut Vec<&'a IndexEntry>,
+    ) {
+        if indices.is_empty() {
+            return;
+        }
+        let mid = indices.start + indices.len() / 2;
+        if self.max_end_beats[mid] < range.start {
+            return;
+        }
+
+        self.find_intervals(indices.start..mid, range, entries);
+
+        // Every later interval starts after this one.
+        let entry = &self.intervals[mid];
+        if entry.beat >= range.end {
+            return;
+        }
+        if range.contains(&entry.beat) || entry.end_beat > range.start {
+            entries.push(entry);
+        }
+
+        self.find_intervals(mid + 1..indices.end, range, entries);
+    }
+
+    /// Returns the index of the first [note](Difficulty::notes) of a color that is strictly after a beat.
+    pub fn get_next_note(&self, color: NoteColor, beat: f32) -> Option<usize> {
+        let (_, entries) = self.notes.iter().find(|(other, _)| *other == color)?;
+        let index = entries.partition_point(|entry| entry.beat <= beat);
+
+        match entries.get(index)?.object {
+            ObjectRef::Note(i) => Some(i),
+            _ => None,
+        }
+    }
+}
+
+impl Difficulty {
+    /// Returns a [`TimeIndex`] for quickly finding objects by beat.
+    pub fn get_time_index(&self) -> TimeIndex {
+        TimeIndex::new(self)
+    }
+}
+
+fn add_points<T: Timed>(
+    entries: &mut Vec<IndexEntry>,
+    objects: &[T],
+    get_ref: fn(usize) -> ObjectRef,
+) {
+    entries.extend(objects.iter().enumerate().map(|(i, object)| IndexEntry {
+        beat: object.get_beat(),
+        end_beat: object.get_beat(),
+        object: get_ref(i),
+    }));
+}
+
+fn add_intervals<T: Duration>(
+    entries: &mut Vec<IndexEntry>,
+    objects: &[T],
+    get_ref: fn(usize) -> ObjectRef,
+) {
+    entries.extend(objects.iter().enumerate().map(|(i, object)| IndexEntry {
+        beat: object.get_beat(),
+        end_beat: object.get_end_beat(),
+        object: get_ref(i),
+    }));
+}
+
+fn sort_entries(entries: &mut [IndexEntry]) {
+    entries.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+}
+
+/// Sets the latest end beat of each sub-tree, returning the latest end beat of the whole sub-tree.
+fn build_max_end_beats(
+    entries: &[IndexEntry],
+    max_end_beats: &mut [f32],
+    indices: Range<usize>,
+) -> f32 {
+    if indices.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let mid = indices.start + indices.len() / 2;
+
+    let left = build_max_end_beats(entries, max_end_beats, indices.start..mid);
+    let right = build_max_end_beats(entries, max_end_beats, mid + 1..indices.end);
+    max_end_beats[mid] = entries[mid].end_beat.max(left).max(right);
+    max_end_beats[mid]
+}
+
+/// Returns the entries that start within a range, assuming that they are sorted by beat.
+fn get_starting_in<'a>(entries: &'a [IndexEntry], range: &Range<f32>) -> &'a [IndexEntry] {
+    let start = entries.partition_point(|entry| entry.beat < range.start);
+    let end = entries.partition_point(|entry| entry.beat < range.end);
+    &entries[start..end.max(start)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::lightshow::BasicEvent;
+    use crate::difficulty::playfield::{Note, Wall};
+
+    fn get_note(beat: f32, color: NoteColor) -> Note {
+        Note {
+            beat,
+            color,
+            ..Default::default()
+        }
+    }
+
+    fn get_difficulty() -> Difficulty {
+        Difficulty {
+            notes: vec![
+                get_note(4.0, NoteColor::Left),
+                get_note(1.0, NoteColor::Right),
+                get_note(2.0, NoteColor::Left),
+            ],
+            walls: vec![
+                Wall {
+                    beat: 0.0,
+                    duration: 8.0,
+                    ..Default::default()
+                },
+                Wall {
+                    beat: 1.0,
+                    duration: 0.5,
+                    ..Default::default()
+                },
+            ],
+            basic_events: vec![BasicEvent {
+                beat: 3.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn objects_in_range() {
+        let index = get_difficulty().get_time_index();
+        assert_eq!(index.len(), 6);
+
+        assert_eq!(
+            index.get_objects_in(2.0..4.0),
+            vec![
+                ObjectRef::Wall(0),
+                ObjectRef::Note(2),
+                ObjectRef::BasicEvent(0),
+            ]
+        );
+        assert_eq!(
+            index.get_objects_in(1.0..1.5),
+            vec![ObjectRef::Wall(0), ObjectRef::Note(1), ObjectRef::Wall(1)]
+        );
+        assert_eq!(index.get_objects_in(8.0..10.0), vec![]);
+    }
+
+    #[test]
+    fn long_wall() {
+        let mut difficulty = Difficulty {
+            walls: (0..100)
+                .map(|i| Wall {
+                    beat: i as f32,
+                    duration: 0.5,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        difficulty.walls.push(Wall {
+            beat: 0.0,
+            duration: 1000.0,
+            ..Default::default()
+        });
+        let index = difficulty.get_time_index();
+
+        assert_eq!(index.get_objects_in(50.6..51.0), vec![ObjectRef::Wall(100)]);
+        assert_eq!(
+            index.get_objects_in(50.0..50.5),
+            vec![ObjectRef::Wall(100), ObjectRef::Wall(50)]
+        );
+        assert_eq!(
+            index.get_objects_in(999.0..2000.0),
+            vec![ObjectRef::Wall(100)]
+        );
+    }
+
+    #[test]
+    fn next_note() {
+        let index = get_difficulty().get_time_index();
+
+        assert_eq!(index.get_next_note(NoteColor::Left, 0.0), Some(2));
+        assert_eq!(index.get_next_note(NoteColor::Left, 2.0), Some(0));
+        assert_eq!(index.get_next_note(NoteColor::Left, 4.0), None);
+        assert_eq!(index.get_next_note(NoteColor::Right, 0.0), Some(1));
+        assert_eq!(index.get_next_note(NoteColor::Undefined(5), 0.0), None);
+    }
+}
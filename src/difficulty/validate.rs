@@ -0,0 +1,726 @@
+//! Finds objects that are invalid, or that the game will not handle correctly.
+
+use crate::difficulty::gameplay_event::ExecutionTime;
+use crate::difficulty::lightshow::easing::Easing;
+use crate::difficulty::lightshow::filter::{Filter, FilterType};
+use crate::difficulty::lightshow::group::color::{ColorTransitionType, LightColor};
+use crate::difficulty::lightshow::group::rotation::RotationDirection;
+use crate::difficulty::lightshow::{DistributionType, EventAxis, TransitionType};
+use crate::difficulty::playfield::{CutDirection, MidAnchorMode, NoteColor};
+use crate::difficulty::{Difficulty, ObjectRef};
+use serde::Serialize;
+
+/// The number of rows in the grid.
+const ROW_COUNT: i32 = 3;
+/// The number of columns in the grid.
+const COL_COUNT: i32 = 4;
+/// The maximum height of a wall, measured from the bottom row.
+const MAX_WALL_HEIGHT: i32 = 5;
+/// Objects with a time difference less than this are considered to be at the same beat.
+const BEAT_EPSILON: f32 = 0.001;
+
+/// Controls which checks are used when [validating](Difficulty::validate_with) a difficulty.
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct ValidationOptions {
+    /// The light group IDs that exist in the map's environment.
+    ///
+    /// If `None`, event box group IDs are not checked, as they depend on the environment.
+    pub light_group_ids: Option<Vec<i32>>,
+}
+
+/// A single problem found when [validating](Difficulty::validate) a difficulty.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct Diagnostic {
+    /// The object with the problem.
+    pub object: ObjectRef,
+    /// The beat of the object, or the part of the object with the problem.
+    pub beat: f32,
+    pub kind: DiagnosticKind,
+}
+
+/// The kind of problem found by [validation](Difficulty::validate).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum DiagnosticKind {
+    /// The object (or the tail of the object) is outside of the 4x3 grid.
+    OutOfGrid { row: i32, col: i32 },
+    /// A field has a value that the game does not recognise.
+    UndefinedValue { field: String, value: i32 },
+    /// The object is at the same beat and position as another note or bomb.
+    Stacked { other: ObjectRef },
+    /// A wall with a duration of zero or less.
+    NonPositiveDuration { duration: f32 },
+    /// A wall that is outside of the lanes, or has a width or height of zero or less.
+    WallOutOfLanes,
+    /// A chain with a link count of less than one.
+    InvalidLinkCount { link_count: i32 },
+    /// A chain with a link squish of zero or less, which will crash the game.
+    InvalidLinkSquish { link_squish: f32 },
+    /// An arc or chain that ends before it starts.
+    TailBeforeHead { tail_beat: f32 },
+    /// An event box that references a light group that does not exist in the environment.
+    UnknownGroupId { group_id: i32 },
+}
+
+/// Implemented by loose enums, to find values that the game does not recognise.
+trait MaybeUndefined {
+    fn get_undefined(&self) -> Option<i32>;
+}
+
+macro_rules! impl_maybe_undefined {
+    ($($ty:ident),*) => {
+        $(
+            impl MaybeUndefined for $ty {
+                fn get_undefined(&self) -> Option<i32> {
+                    match self {
+                        $ty::Undefined(value) => Some(*value),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_maybe_undefined!(
+    NoteColor,
+    CutDirection,
+    MidAnchorMode,
+    ExecutionTime,
+    FilterType,
+    DistributionType,
+    Easing,
+    ColorTransitionType,
+    LightColor,
+    TransitionType,
+    EventAxis,
+    RotationDirection
+);
+
+impl<T: MaybeUndefined> MaybeUndefined for Option<T> {
+    fn get_undefined(&self) -> Option<i32> {
+        self.as_ref().and_then(T::get_undefined)
+    }
+}
+
+impl Difficulty {
+    /// Returns every problem found in the difficulty, sorted by beat.
+    ///
+    /// Light group IDs are not checked, as they depend on the environment.
+    /// To check them, use [`validate_with`](Self::validate_with).
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        self.validate_with(&ValidationOptions::default())
+    }
+
+    /// Returns every problem found in the difficulty, sorted by beat.
+    pub fn validate_with(&self, options: &ValidationOptions) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        validate_playfield(self, &mut diagnostics);
+        validate_stacks(self, &mut diagnostics);
+        validate_lightshow(self, options, &mut diagnostics);
+
+        for (i, event) in self.lane_rotation_events.iter().enumerate() {
+            let object = ObjectRef::LaneRotationEvent(i);
+            check_defined(
+                &mut diagnostics,
+                object,
+                event.beat,
+                "execution_time",
+                &event.execution_time,
+            );
+        }
+
+        diagnostics.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        diagnostics
+    }
+}
+
+fn push(diagnostics: &mut Vec<Diagnostic>, object: ObjectRef, beat: f32, kind: DiagnosticKind) {
+    diagnostics.push(Diagnostic { object, beat, kind });
+}
+
+fn check_defined(
+    diagnostics: &mut Vec<Diagnostic>,
+    object: ObjectRef,
+    beat: f32,
+    field: &str,
+    value: &impl MaybeUndefined,
+) {
+    if let Some(value) = value.get_undefined() {
+        let field = field.to_string();
+        push(
+            diagnostics,
+            object,
+            beat,
+            DiagnosticKind::UndefinedValue { field, value },
+        );
+    }
+}
+
+fn check_grid(diagnostics: &mut Vec<Diagnostic>, object: ObjectRef, beat: f32, row: i32, col: i32) {
+    if !(0..ROW_COUNT).contains(&row) || !(0..COL_COUNT).contains(&col) {
+        push(
+            diagnostics,
+            object,
+            beat,
+            DiagnosticKind::OutOfGrid { row, col },
+        );
+    }
+}
+
+fn check_tail(diagnostics: &mut Vec<Diagnostic>, object: ObjectRef, beat: f32, tail_beat: f32) {
+    if tail_beat < beat {
+        push(
+            diagnostics,
+            object,
+            beat,
+            DiagnosticKind::TailBeforeHead { tail_beat },
+        );
+    }
+}
+
+fn validate_playfield(difficulty: &Difficulty, diagnostics: &mut Vec<Diagnostic>) {
+    for (i, note) in difficulty.notes.iter().enumerate() {
+        let object = ObjectRef::Note(i);
+        check_grid(diagnostics, object, note.beat, note.row, note.col);
+        check_defined(diagnostics, object, note.beat, "color", &note.color);
+        check_defined(diagnostics, object, note.beat, "direction", &note.direction);
+    }
+
+    for (i, bomb) in difficulty.bombs.iter().enumerate() {
+        check_grid(
+            diagnostics,
+            ObjectRef::Bomb(i),
+            bomb.beat,
+            bomb.row,
+            bomb.col,
+        );
+    }
+
+    for (i, wall) in difficulty.walls.iter().enumerate() {
+        let object = ObjectRef::Wall(i);
+
+        if wall.duration <= 0.0 {
+            let duration = wall.duration;
+            push(
+                diagnostics,
+                object,
+                wall.beat,
+                DiagnosticKind::NonPositiveDuration { duration },
+            );
+        }
+
+        let in_lanes = wall.col >= 0 && wall.width > 0 && wall.col + wall.width <= COL_COUNT;
+        let in_rows = (0..ROW_COUNT).contains(&wall.row)
+            && wall.height > 0
+            && wall.row + wall.height <= MAX_WALL_HEIGHT;
+        if !in_lanes || !in_rows {
+            push(
+                diagnostics,
+                object,
+                wall.beat,
+                DiagnosticKind::WallOutOfLanes,
+            );
+        }
+    }
+
+    for (i, arc) in difficulty.arcs.iter().enumerate() {
+        let object = ObjectRef::Arc(i);
+        check_grid(diagnostics, object, arc.beat, arc.row, arc.col);
+        check_grid(
+            diagnostics,
+            object,
+            arc.tail_beat,
+            arc.tail_row,
+            arc.tail_col,
+        );
+        check_tail(diagnostics, object, arc.beat, arc.tail_beat);
+        check_defined(diagnostics, object, arc.beat, "color", &arc.color);
+        check_defined(diagnostics, object, arc.beat, "direction", &arc.direction);
+        check_defined(
+            diagnostics,
+            object,
+            arc.tail_beat,
+            "tail_direction",
+            &arc.tail_direction,
+        );
+        check_defined(
+            diagnostics,
+            object,
+            arc.beat,
+            "mid_anchor_mode",
+            &arc.mid_anchor_mode,
+        );
+    }
+
+    for (i, chain) in difficulty.chains.iter().enumerate() {
+        let object = ObjectRef::Chain(i);
+        check_grid(diagnostics, object, chain.beat, chain.row, chain.col);
+        check_grid(
+            diagnostics,
+            object,
+            chain.tail_beat,
+            chain.tail_row,
+            chain.tail_col,
+        );
+        check_tail(diagnostics, object, chain.beat, chain.tail_beat);
+        check_defined(diagnostics, object, chain.beat, "color", &chain.color);
+        check_defined(
+            diagnostics,
+            object,
+            chain.beat,
+            "direction",
+            &chain.direction,
+        );
+
+        if chain.link_count < 1 {
+            let link_count = chain.link_count;
+            push(
+                diagnostics,
+                object,
+                chain.beat,
+                DiagnosticKind::InvalidLinkCount { link_count },
+            );
+        }
+
+        if chain.link_squish <= 0.0 {
+            let link_squish = chain.link_squish;
+            push(
+                diagnostics,
+                object,
+                chain.beat,
+                DiagnosticKind::InvalidLinkSquish { link_squish },
+            );
+        }
+    }
+}
+
+/// Finds notes and bombs that are at the same beat and position as an earlier note or bomb.
+fn validate_stacks(difficulty: &Difficulty, diagnostics: &mut Vec<Diagnostic>) {
+    let notes = difficulty.notes.iter().enumerate();
+    let bombs = difficulty.bombs.iter().enumerate();
+
+    let mut objects: Vec<(ObjectRef, f32, i32, i32)> = notes
+        .map(|(i, note)| (ObjectRef::Note(i), note.beat, note.row, note.col))
+        .chain(bombs.map(|(i, bomb)| (ObjectRef::Bomb(i), bomb.beat, bomb.row, bomb.col)))
+        .collect();
+    objects.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    for (i, (object, beat, row, col)) in objects.iter().enumerate() {
+        let other = objects[..i]
+            .iter()
+            .rev()
+            .take_while(|(_, other_beat, _, _)| beat - other_beat < BEAT_EPSILON)
+            .find(|(_, _, other_row, other_col)| other_row == row && other_col == col);
+
+        if let Some((other, _, _, _)) = other {
+            push(
+                diagnostics,
+                *object,
+                *beat,
+                DiagnosticKind::Stacked { other: *other },
+            );
+        }
+    }
+}
+
+/// Checks the parts of an event box that are shared by all event box types.
+fn check_event_box<'a>(
+    diagnostics: &mut Vec<Diagnostic>,
+    options: &ValidationOptions,
+    object: ObjectRef,
+    beat: f32,
+    group_id: i32,
+    filters: impl Iterator<Item = &'a Filter>,
+) {
+    if let Some(group_ids) = &options.light_group_ids
+        && !group_ids.contains(&group_id)
+    {
+        push(
+            diagnostics,
+            object,
+            beat,
+            DiagnosticKind::UnknownGroupId { group_id },
+        );
+    }
+
+    for filter in filters {
+        check_defined(
+            diagnostics,
+            object,
+            beat,
+            "filter_type",
+            &filter.filter_type,
+        );
+    }
+}
+
+fn validate_lightshow(
+    difficulty: &Difficulty,
+    options: &ValidationOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (i, event_box) in difficulty.color_event_boxes.iter().enumerate() {
+        let object = ObjectRef::ColorEventBox(i);
+        let beat = event_box.beat;
+        let filters = event_box.groups.iter().map(|group| &group.filter);
+        check_event_box(
+            diagnostics,
+            options,
+            object,
+            beat,
+            event_box.group_id,
+            filters,
+        );
+
+        for group in &event_box.groups {
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "beat_dist_type",
+                &group.beat_dist_type,
+            );
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "bright_dist_type",
+                &group.bright_dist_type,
+            );
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "bright_dist_easing",
+                &group.bright_dist_easing,
+            );
+
+            for data in &group.data {
+                let beat = beat + data.beat_offset;
+                check_defined(
+                    diagnostics,
+                    object,
+                    beat,
+                    "transition_type",
+                    &data.transition_type,
+                );
+                check_defined(diagnostics, object, beat, "color", &data.color);
+            }
+        }
+    }
+
+    for (i, event_box) in difficulty.rotation_event_boxes.iter().enumerate() {
+        let object = ObjectRef::RotationEventBox(i);
+        let beat = event_box.beat;
+        let filters = event_box.groups.iter().map(|group| &group.filter);
+        check_event_box(
+            diagnostics,
+            options,
+            object,
+            beat,
+            event_box.group_id,
+            filters,
+        );
+
+        for group in &event_box.groups {
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "beat_dist_type",
+                &group.beat_dist_type,
+            );
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "rotation_dist_type",
+                &group.rotation_dist_type,
+            );
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "rotation_dist_easing",
+                &group.rotation_dist_easing,
+            );
+            check_defined(diagnostics, object, beat, "axis", &group.axis);
+
+            for data in &group.data {
+                let beat = beat + data.beat_offset;
+                check_defined(
+                    diagnostics,
+                    object,
+                    beat,
+                    "transition_type",
+                    &data.transition_type,
+                );
+                check_defined(diagnostics, object, beat, "easing", &data.easing);
+                check_defined(diagnostics, object, beat, "direction", &data.direction);
+            }
+        }
+    }
+
+    let translation_event_boxes = difficulty.translation_event_boxes.iter().flatten();
+    for (i, event_box) in translation_event_boxes.enumerate() {
+        let object = ObjectRef::TranslationEventBox(i);
+        let beat = event_box.beat;
+        let filters = event_box.groups.iter().map(|group| &group.filter);
+        check_event_box(
+            diagnostics,
+            options,
+            object,
+            beat,
+            event_box.group_id,
+            filters,
+        );
+
+        for group in &event_box.groups {
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "beat_dist_type",
+                &group.beat_dist_type,
+            );
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "translation_dist_type",
+                &group.translation_dist_type,
+            );
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "translation_dist_easing",
+                &group.translation_dist_easing,
+            );
+            check_defined(diagnostics, object, beat, "axis", &group.axis);
+
+            for data in &group.data {
+                let beat = beat + data.beat_offset;
+                check_defined(
+                    diagnostics,
+                    object,
+                    beat,
+                    "transition_type",
+                    &data.transition_type,
+                );
+                check_defined(diagnostics, object, beat, "easing", &data.easing);
+            }
+        }
+    }
+
+    let fx_event_boxes = difficulty
+        .fx_event_boxes
+        .iter()
+        .flat_map(|container| container.iter());
+    for (i, event_box) in fx_event_boxes.enumerate() {
+        let object = ObjectRef::FxEventBox(i);
+        let beat = event_box.beat;
+        let filters = event_box.groups.iter().map(|group| &group.filter);
+        check_event_box(
+            diagnostics,
+            options,
+            object,
+            beat,
+            event_box.group_id,
+            filters,
+        );
+
+        for group in &event_box.groups {
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "beat_dist_type",
+                &group.beat_dist_type,
+            );
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "fx_dist_type",
+                &group.fx_dist_type,
+            );
+            check_defined(
+                diagnostics,
+                object,
+                beat,
+                "fx_dist_easing",
+                &group.fx_dist_easing,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::lightshow::group::color::ColorEventBox;
+    use crate::difficulty::playfield::{Arc, Bomb, Chain, Note, Wall};
+
+    fn get_kinds(diagnostics: &[Diagnostic]) -> Vec<DiagnosticKind> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.kind.clone())
+            .collect()
+    }
+
+    #[test]
+    fn valid_difficulty() {
+        let difficulty = Difficulty {
+            notes: vec![Note::default()],
+            bombs: vec![Bomb {
+                beat: 1.0,
+                ..Default::default()
+            }],
+            walls: vec![Wall::default()],
+            arcs: vec![Arc::default()],
+            chains: vec![Chain::default()],
+            color_event_boxes: vec![ColorEventBox::default()],
+            ..Default::default()
+        };
+
+        assert_eq!(difficulty.validate(), vec![]);
+    }
+
+    #[test]
+    fn notes_out_of_grid_and_undefined() {
+        let difficulty = Difficulty {
+            notes: vec![Note {
+                beat: 2.0,
+                row: 3,
+                col: -1,
+                direction: CutDirection::Undefined(12),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let diagnostics = difficulty.validate();
+        assert_eq!(diagnostics[0].object, ObjectRef::Note(0));
+        assert_eq!(diagnostics[0].beat, 2.0);
+        assert_eq!(
+            get_kinds(&diagnostics),
+            vec![
+                DiagnosticKind::OutOfGrid { row: 3, col: -1 },
+                DiagnosticKind::UndefinedValue {
+                    field: "direction".to_string(),
+                    value: 12
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn stacked_notes_and_bombs() {
+        let difficulty = Difficulty {
+            notes: vec![Note::default(), Note::default()],
+            bombs: vec![Bomb::default()],
+            ..Default::default()
+        };
+
+        let diagnostics = difficulty.validate();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].object, ObjectRef::Note(1));
+        assert_eq!(
+            diagnostics[1].kind,
+            DiagnosticKind::Stacked {
+                other: ObjectRef::Note(1)
+            }
+        );
+    }
+
+    #[test]
+    fn walls() {
+        let difficulty = Difficulty {
+            walls: vec![
+                Wall {
+                    duration: 0.0,
+                    ..Default::default()
+                },
+                Wall {
+                    col: 3,
+                    width: 2,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_kinds(&difficulty.validate()),
+            vec![
+                DiagnosticKind::NonPositiveDuration { duration: 0.0 },
+                DiagnosticKind::WallOutOfLanes,
+            ]
+        );
+    }
+
+    #[test]
+    fn chains_and_arcs() {
+        let difficulty = Difficulty {
+            arcs: vec![Arc {
+                beat: 2.0,
+                tail_beat: 1.0,
+                ..Default::default()
+            }],
+            chains: vec![Chain {
+                beat: 3.0,
+                tail_beat: 3.5,
+                link_count: 0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_kinds(&difficulty.validate()),
+            vec![
+                DiagnosticKind::TailBeforeHead { tail_beat: 1.0 },
+                DiagnosticKind::InvalidLinkCount { link_count: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn light_group_ids() {
+        let difficulty = Difficulty {
+            color_event_boxes: vec![ColorEventBox {
+                group_id: 4,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(difficulty.validate(), vec![]);
+
+        let options = ValidationOptions {
+            light_group_ids: Some(vec![0, 1, 2]),
+        };
+        assert_eq!(
+            get_kinds(&difficulty.validate_with(&options)),
+            vec![DiagnosticKind::UnknownGroupId { group_id: 4 }]
+        );
+    }
+}
@@ -0,0 +1,470 @@
+//! Generates difficulties for other [characteristics](Characteristic) from a Standard difficulty.
+//!
+//! - [OneSaber](Difficulty::to_one_saber) removes every note, arc and chain of one color.
+//! - [NoArrows](Difficulty::to_no_arrows) replaces every cut direction with [`CutDirection::Any`],
+//!   and merges stacks/sliders of the same color (which are unreadable as dots) into a single note.
+//! - [90Degree and 360Degree](Difficulty::to_rotation) add [`LaneRotationEvent`]s that follow the flow of the notes,
+//!   turning towards the side of the grid that the notes are on. Walls are cut short so that they never span a rotation.
+//!
+//! Generated difficulties can be added to an [info file](Beatmap) using [`Beatmap::add_generated_set`].
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::gameplay_event::{ExecutionTime, LaneRotationEvent};
+use crate::difficulty::playfield::{CutDirection, NoteColor};
+use crate::info::{Beatmap, Characteristic, DifficultySet};
+use crate::timing_traits::Duration;
+
+/// Notes of the same color with a time difference (in beats) less than this are part of a stack/slider.
+const STACK_THRESHOLD: f32 = 0.0625 + 0.001;
+/// The number of degrees added by a single generated rotation.
+const ROTATION_STEP: f32 = 15.0;
+/// The minimum number of beats between two generated rotations.
+const ROTATION_INTERVAL: f32 = 2.0;
+/// Walls that are cut shorter than this (in beats) by a rotation are removed.
+const MIN_WALL_DURATION: f32 = 0.125;
+
+/// Decides which characteristic a generated difficulty will have, and how it is [generated](Self::generate).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub enum Generator {
+    /// Keeps only the right (blue by default) saber's notes, matching the game's OneSaber mode.
+    OneSaber,
+    /// Makes every note cuttable in any direction, merging stacks and sliders into a single note.
+    NoArrows,
+    /// Adds rotations that stay within 45 degrees on either side, matching the game's 90Degree mode.
+    Rotate90,
+    /// Adds rotations without any limit, matching the game's 360Degree mode.
+    Rotate360,
+}
+
+impl Generator {
+    /// Returns the characteristic of difficulties made by the generator.
+    pub fn get_characteristic(&self) -> Characteristic {
+        match self {
+            Generator::OneSaber => Characteristic::OneSaber,
+            Generator::NoArrows => Characteristic::NoArrows,
+            Generator::Rotate90 => Characteristic::Rotate90,
+            Generator::Rotate360 => Characteristic::Rotate360,
+        }
+    }
+
+    /// Generates a new difficulty from a Standard difficulty.
+    pub fn generate(&self, difficulty: &Difficulty) -> Difficulty {
+        match self {
+            Generator::OneSaber => difficulty.to_one_saber(NoteColor::Right),
+            Generator::NoArrows => difficulty.to_no_arrows(),
+            Generator::Rotate90 => difficulty.to_rotation(Some(90.0)),
+            Generator::Rotate360 => difficulty.to_rotation(None),
+        }
+    }
+}
+
+impl Difficulty {
+    /// Returns a copy of the difficulty, with only the notes, arcs and chains of the given color.
+    pub fn to_one_saber(&self, color: NoteColor) -> Self {
+        let mut difficulty = self.clone();
+        difficulty.notes.retain(|note| note.color == color);
+        difficulty.arcs.retain(|arc| arc.color == color);
+        difficulty.chains.retain(|chain| chain.color == color);
+        difficulty
+    }
+
+    /// Returns a copy of the difficulty where every note can be cut in any direction.
+    ///
+    /// Notes that are stacked with an earlier note of the same color are removed.
+    /// Notes on the same beat are ordered by row then column, so the lowest note of a stack is kept.
+    /// Notes that are the head of a chain are always kept, replacing the kept note of their stack if needed.
+    pub fn to_no_arrows(&self) -> Self {
+        let mut difficulty = self.clone();
+
+        difficulty.notes.sort_by(|a, b| {
+            (a.beat.total_cmp(&b.beat))
+                .then(a.row.cmp(&b.row))
+                .then(a.col.cmp(&b.col))
+        });
+        let heads: Vec<bool> = (difficulty.notes.iter())
+            .map(|note| {
+                difficulty.chains.iter().any(|chain| {
+                    chain.beat == note.beat
+                        && chain.row == note.row
+                        && chain.col == note.col
+                        && chain.color == note.color
+                })
+            })
+            .collect();
+
+        // The beat of the last note and the index of the kept note, for each color's current stack.
+        let mut stacks: [Option<(f32, usize)>; 2] = [None, None];
+        let mut keep = vec![true; difficulty.notes.len()];
+        for (i, note) in difficulty.notes.iter().enumerate() {
+            let Some(index) = get_color_index(note.color) else {
+                continue;
+            };
+
+            match &mut stacks[index] {
+                Some((last, kept)) if note.beat - *last < STACK_THRESHOLD => {
+                    *last = note.beat;
+                    if !heads[i] {
+                        keep[i] = false;
+                    } else if !heads[*kept] {
+                        keep[*kept] = false;
+                        *kept = i;
+                    }
+                }
+                stack => *stack = Some((note.beat, i)),
+            }
+        }
+        let mut keep = keep.into_iter();
+        difficulty.notes.retain(|_| keep.next().unwrap_or(true));
+
+        for note in &mut difficulty.notes {
+            note.direction = CutDirection::Any;
+            note.angle_offset = 0.0;
+        }
+        for arc in &mut difficulty.arcs {
+            arc.direction = CutDirection::Any;
+            arc.tail_direction = CutDirection::Any;
+        }
+        for chain in &mut difficulty.chains {
+            chain.direction = CutDirection::Any;
+        }
+
+        difficulty
+    }
+
+    /// Returns a copy of the difficulty with generated [`LaneRotationEvent`]s, replacing any existing ones (including V2 rotation events).
+    ///
+    /// If a limit (in degrees) is given, the total rotation will stay within half of the limit on either side.
+    /// Use a limit of 90 for 90Degree difficulties, and `None` for 360Degree difficulties.
+    pub fn to_rotation(&self, limit: Option<f32>) -> Self {
+        let mut difficulty = self.clone();
+        difficulty.lane_rotation_events.clear();
+        (difficulty.basic_events)
+            .retain(|event| LaneRotationEvent::from_basic_event(event).is_none());
+
+        let mut notes: Vec<_> = self.notes.iter().collect();
+        notes.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        let mut rotation = 0.0;
+        let mut last_beat = f32::NEG_INFINITY;
+        for (i, note) in notes.iter().enumerate() {
+            if note.beat - last_beat < ROTATION_INTERVAL
+                || i > 0 && note.beat - notes[i - 1].beat < STACK_THRESHOLD
+            {
+                continue;
+            }
+
+            // Turn towards the side of the grid that the notes at this beat are on.
+            let cols: Vec<f32> = notes[i..]
+                .iter()
+                .take_while(|other| other.beat - note.beat < STACK_THRESHOLD)
+                .map(|other| other.col as f32)
+                .collect();
+            let average_col = cols.iter().sum::<f32>() / cols.len() as f32;
+
+            let mut degrees = if average_col < 1.5 {
+                -ROTATION_STEP
+            } else if average_col > 1.5 {
+                ROTATION_STEP
+            } else {
+                continue;
+            };
+            if let Some(limit) = limit
+                && (rotation + degrees).abs() > limit / 2.0
+            {
+                degrees = -degrees;
+            }
+
+            rotation += degrees;
+            last_beat = note.beat;
+            difficulty.lane_rotation_events.push(LaneRotationEvent {
+                beat: note.beat,
+                execution_time: ExecutionTime::Early,
+                degrees,
+            });
+        }
+
+        let rotation_beats: Vec<f32> = difficulty
+            .lane_rotation_events
+            .iter()
+            .map(|event| event.beat)
+            .collect();
+        difficulty.walls.retain_mut(|wall| {
+            let end_beat = wall.get_end_beat();
+            let cut = rotation_beats
+                .iter()
+                .find(|beat| **beat > wall.beat && **beat < end_beat);
+
+            match cut {
+                None => true,
+                Some(beat) => {
+                    wall.duration = beat - wall.beat;
+                    wall.duration >= MIN_WALL_DURATION
+                }
+            }
+        });
+
+        difficulty
+    }
+}
+
+fn get_color_index(color: NoteColor) -> Option<usize> {
+    match color {
+        NoteColor::Left => Some(0),
+        NoteColor::Right => Some(1),
+        NoteColor::Undefined(_) => None,
+    }
+}
+
+impl Beatmap {
+    /// Generates a difficulty set from the Standard difficulty set, and adds it to the info file,
+    /// replacing any existing set with the same characteristic.
+    ///
+    /// The `standard` difficulties must be in the same order as the Standard set's [difficulties](DifficultySet::difficulties).
+    /// Each generated difficulty's file name is the difficulty name followed by the characteristic, i.e. `ExpertOneSaber.dat`.
+    ///
+    /// Returns the generated difficulties, in the same order, or `None` if there is no Standard set
+    /// or the number of `standard` difficulties does not match it. In that case, the info file is unchanged.
+    pub fn add_generated_set(
+        &mut self,
+        generator: Generator,
+        standard: &[Difficulty],
+    ) -> Option<Vec<Difficulty>> {
+        let source = self
+            .difficulty_sets
+            .iter()
+            .find(|set| set.characteristic == Characteristic::Standard)?;
+        if source.difficulties.len() != standard.len() {
+            return None;
+        }
+
+        let characteristic = generator.get_characteristic();
+        let characteristic_name = String::from(characteristic.clone());
+
+        let infos = source
+            .difficulties
+            .iter()
+            .map(|info| {
+                let mut info = info.clone();
+                info.file = format!("{}{characteristic_name}.dat", info.name);
+                info
+            })
+            .collect();
+        let difficulties = standard
+            .iter()
+            .map(|difficulty| generator.generate(difficulty))
+            .collect();
+
+        let set = DifficultySet {
+            characteristic,
+            difficulties: infos,
+        };
+        match (self.difficulty_sets.iter_mut())
+            .find(|other| other.characteristic == set.characteristic)
+        {
+            Some(existing) => *existing = set,
+            None => self.difficulty_sets.push(set),
+        }
+
+        Some(difficulties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::playfield::{Arc, Chain, Note, Wall};
+    use crate::info::DifficultyInfo;
+
+    fn get_note(beat: f32, col: i32, color: NoteColor) -> Note {
+        Note {
+            beat,
+            col,
+            color,
+            direction: CutDirection::Down,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn one_saber() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, 1, NoteColor::Left),
+                get_note(0.0, 2, NoteColor::Right),
+            ],
+            arcs: vec![Arc::default()],
+            ..Default::default()
+        };
+
+        let generated = difficulty.to_one_saber(NoteColor::Right);
+        assert_eq!(generated.notes, vec![difficulty.notes[1].clone()]);
+        assert!(generated.arcs.is_empty());
+    }
+
+    #[test]
+    fn no_arrows_resolves_stacks() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, 1, NoteColor::Left),
+                get_note(0.0, 1, NoteColor::Left),
+                get_note(0.03125, 2, NoteColor::Left),
+                get_note(0.0, 2, NoteColor::Right),
+                get_note(1.0, 1, NoteColor::Left),
+            ],
+            ..Default::default()
+        };
+
+        let generated = difficulty.to_no_arrows();
+        let beats: Vec<(f32, NoteColor)> = (generated.notes.iter())
+            .map(|note| (note.beat, note.color))
+            .collect();
+        assert_eq!(
+            beats,
+            vec![
+                (0.0, NoteColor::Left),
+                (0.0, NoteColor::Right),
+                (1.0, NoteColor::Left),
+            ]
+        );
+        assert!((generated.notes.iter()).all(|note| note.direction == CutDirection::Any));
+    }
+
+    #[test]
+    fn no_arrows_keeps_chain_heads() {
+        let mut head = get_note(0.0, 2, NoteColor::Left);
+        head.row = 1;
+        let mut notes = vec![
+            get_note(0.0, 1, NoteColor::Left),
+            head.clone(),
+            get_note(0.0, 3, NoteColor::Left),
+        ];
+        let chain = Chain {
+            beat: 0.0,
+            row: 1,
+            col: 2,
+            color: NoteColor::Left,
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let difficulty = Difficulty {
+                notes: notes.clone(),
+                chains: vec![chain.clone()],
+                ..Default::default()
+            };
+            let generated = difficulty.to_no_arrows();
+            let positions: Vec<(i32, i32)> = (generated.notes.iter())
+                .map(|note| (note.row, note.col))
+                .collect();
+            assert_eq!(positions, vec![(1, 2)]);
+            assert_eq!(generated.chains.len(), 1);
+            notes.reverse();
+        }
+
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, 3, NoteColor::Left),
+                get_note(0.0, 1, NoteColor::Left),
+            ],
+            ..Default::default()
+        };
+        let generated = difficulty.to_no_arrows();
+        assert_eq!(generated.notes[0].col, 1);
+    }
+
+    #[test]
+    fn rotation_follows_notes() {
+        let difficulty = Difficulty {
+            notes: vec![
+                get_note(0.0, 3, NoteColor::Right),
+                get_note(1.0, 0, NoteColor::Left),
+                get_note(2.0, 0, NoteColor::Left),
+                get_note(4.0, 3, NoteColor::Right),
+            ],
+            walls: vec![
+                Wall {
+                    beat: 1.0,
+                    duration: 2.0,
+                    ..Default::default()
+                },
+                Wall {
+                    beat: 1.95,
+                    duration: 1.0,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let generated = difficulty.to_rotation(None);
+        let degrees: Vec<(f32, f32)> = (generated.lane_rotation_events.iter())
+            .map(|event| (event.beat, event.degrees))
+            .collect();
+        assert_eq!(degrees, vec![(0.0, 15.0), (2.0, -15.0), (4.0, 15.0)]);
+
+        // The first wall is cut short, and the second is too short after cutting.
+        assert_eq!(generated.walls.len(), 1);
+        assert_eq!(generated.walls[0].duration, 1.0);
+    }
+
+    #[test]
+    fn rotation_limit() {
+        let difficulty = Difficulty {
+            notes: (0..6)
+                .map(|i| get_note(i as f32 * 2.0, 3, NoteColor::Right))
+                .collect(),
+            ..Default::default()
+        };
+
+        let generated = difficulty.to_rotation(Some(90.0));
+        let degrees: Vec<f32> = (generated.lane_rotation_events.iter())
+            .map(|event| event.degrees)
+            .collect();
+        assert_eq!(degrees, vec![15.0, 15.0, 15.0, -15.0, 15.0, -15.0]);
+    }
+
+    #[test]
+    fn add_set() {
+        let mut beatmap = Beatmap {
+            difficulty_sets: vec![DifficultySet {
+                characteristic: Characteristic::Standard,
+                difficulties: vec![DifficultyInfo {
+                    name: "Expert".to_string(),
+                    file: "ExpertStandard.dat".to_string(),
+                    ..Default::default()
+                }],
+            }],
+            ..Default::default()
+        };
+
+        let generated = beatmap
+            .add_generated_set(Generator::Rotate90, &[Difficulty::default()])
+            .unwrap();
+        assert_eq!(generated.len(), 1);
+        assert_eq!(beatmap.difficulty_sets.len(), 2);
+
+        let set = &beatmap.difficulty_sets[1];
+        assert_eq!(set.characteristic, Characteristic::Rotate90);
+        assert_eq!(set.difficulties[0].file, "Expert90Degree.dat");
+
+        // Mismatched difficulties are rejected, without changing the existing set.
+        assert_eq!(beatmap.add_generated_set(Generator::Rotate90, &[]), None);
+        let too_many = [Difficulty::default(), Difficulty::default()];
+        assert_eq!(
+            beatmap.add_generated_set(Generator::OneSaber, &too_many),
+            None
+        );
+        assert_eq!(beatmap.difficulty_sets.len(), 2);
+        assert_eq!(beatmap.difficulty_sets[1].difficulties.len(), 1);
+
+        assert_eq!(
+            Beatmap::default().add_generated_set(Generator::OneSaber, &[]),
+            None
+        );
+    }
+}
@@ -1,6 +1,7 @@
 //! Defines the structure of a map's `Info.dat` file.
 
 pub mod color_scheme;
+pub mod jump;
 
 #[doc(hidden)]
 pub use color_scheme::*;
@@ -0,0 +1,146 @@
+//! Calculates how far in front of the player objects spawn, matching the game's formulas.
+//!
+//! Objects spawn half a jump before their beat, and despawn half a jump after it.
+//! The length of this half jump depends on the BPM, [NJS](DifficultyInfo::njs),
+//! and [start beat offset](DifficultyInfo::njd):
+//! 1. Start at four beats.
+//! 2. Halve it while the half jump distance (`NJS * seconds per beat * beats`) would be 18 or more.
+//! 3. Add the start beat offset.
+//! 4. Clamp it to a minimum of a quarter beat.
+
+use crate::info::{DifficultyInfo, DifficultyRank};
+
+/// The initial half jump duration, in beats.
+const START_HALF_JUMP_BEATS: f32 = 4.0;
+/// The half jump distance that causes the half jump duration to be halved.
+const MAX_HALF_JUMP_DISTANCE: f32 = 17.999;
+/// The minimum half jump duration, in beats.
+const MIN_HALF_JUMP_BEATS: f32 = 0.25;
+
+impl DifficultyRank {
+    /// Returns the NJS that the game uses when a difficulty's NJS is zero or less.
+    pub fn get_default_njs(&self) -> f32 {
+        match self {
+            DifficultyRank::Expert => 12.0,
+            DifficultyRank::ExpertPlus => 16.0,
+            _ => 10.0,
+        }
+    }
+}
+
+impl DifficultyInfo {
+    /// Returns the NJS used by the game, falling back to the [rank's default](DifficultyRank::get_default_njs)
+    /// if the NJS is zero or less.
+    pub fn get_njs(&self) -> f32 {
+        if self.njs > 0.0 {
+            self.njs
+        } else {
+            self.rank.get_default_njs()
+        }
+    }
+
+    /// Returns the number of beats that an object is visible before (or after) its beat.
+    pub fn get_half_jump_beats(&self, bpm: f32) -> f32 {
+        (get_base_half_jump_beats(bpm, self.get_njs()) + self.njd).max(MIN_HALF_JUMP_BEATS)
+    }
+
+    /// Returns the number of seconds that an object is visible before reaching the player, also known as reaction time.
+    #[doc(alias = "half_jump_duration")]
+    pub fn get_reaction_time(&self, bpm: f32) -> f32 {
+        self.get_half_jump_beats(bpm) * 60.0 / bpm
+    }
+
+    /// Returns the distance (in meters) that an object travels from spawning to despawning.
+    ///
+    /// Objects spawn half of this distance in front of the player.
+    pub fn get_jump_distance(&self, bpm: f32) -> f32 {
+        self.get_njs() * self.get_reaction_time(bpm) * 2.0
+    }
+
+    /// Returns the [start beat offset](DifficultyInfo::njd) needed for a reaction time (in seconds),
+    /// keeping the current NJS.
+    ///
+    /// Reaction times shorter than a quarter beat can not be reached, as the game clamps them.
+    pub fn get_offset_for_reaction_time(&self, bpm: f32, reaction_time: f32) -> f32 {
+        reaction_time * bpm / 60.0 - get_base_half_jump_beats(bpm, self.get_njs())
+    }
+
+    /// Returns the [start beat offset](DifficultyInfo::njd) needed for a jump distance (in meters),
+    /// keeping the current NJS.
+    pub fn get_offset_for_jump_distance(&self, bpm: f32, jump_distance: f32) -> f32 {
+        self.get_offset_for_reaction_time(bpm, jump_distance / self.get_njs() / 2.0)
+    }
+}
+
+/// Returns the half jump duration in beats, before the offset is applied.
+fn get_base_half_jump_beats(bpm: f32, njs: f32) -> f32 {
+    let seconds_per_beat = 60.0 / bpm;
+    let mut beats = START_HALF_JUMP_BEATS;
+
+    while njs * seconds_per_beat * beats > MAX_HALF_JUMP_DISTANCE {
+        beats /= 2.0;
+    }
+
+    beats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_info(njs: f32, njd: f32) -> DifficultyInfo {
+        DifficultyInfo {
+            njs,
+            njd,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn short_jump_keeps_four_beats() {
+        let info = get_info(10.0, 0.0);
+
+        assert_eq!(info.get_half_jump_beats(240.0), 4.0);
+        assert_eq!(info.get_reaction_time(240.0), 1.0);
+        assert_eq!(info.get_jump_distance(240.0), 20.0);
+    }
+
+    #[test]
+    fn fast_map_halves() {
+        // 18 * 0.5 * 4 = 36, 18 * 0.5 * 2 = 18, 18 * 0.5 * 1 = 9.
+        let info = get_info(18.0, 0.0);
+
+        assert_eq!(info.get_half_jump_beats(120.0), 1.0);
+        assert_eq!(info.get_jump_distance(120.0), 18.0);
+    }
+
+    #[test]
+    fn offset_is_clamped() {
+        let info = get_info(16.0, -10.0);
+
+        assert_eq!(info.get_half_jump_beats(120.0), 0.25);
+    }
+
+    #[test]
+    fn default_njs() {
+        let info = DifficultyInfo {
+            rank: DifficultyRank::ExpertPlus,
+            ..Default::default()
+        };
+
+        assert_eq!(info.get_njs(), 16.0);
+    }
+
+    #[test]
+    fn offset_for_reaction_time() {
+        let mut info = get_info(16.0, 0.0);
+
+        let offset = info.get_offset_for_reaction_time(150.0, 0.6);
+        info.njd = offset;
+        assert!((info.get_reaction_time(150.0) - 0.6).abs() < 0.0001);
+
+        let offset = info.get_offset_for_jump_distance(150.0, 24.0);
+        info.njd = offset;
+        assert!((info.get_jump_distance(150.0) - 24.0).abs() < 0.0001);
+    }
+}
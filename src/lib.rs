@@ -1,13 +1,22 @@
 #![doc = include_str!("../README.md")]
 
+pub mod diff;
 pub mod difficulty;
+pub mod generate;
 pub mod info;
 mod loose_bool;
+pub mod spatial;
 pub mod timing_traits;
 
+#[doc(hidden)]
+pub use diff::*;
 #[doc(hidden)]
 pub use difficulty::*;
 #[doc(hidden)]
+pub use generate::*;
+#[doc(hidden)]
 pub use info::*;
 #[doc(hidden)]
+pub use spatial::*;
+#[doc(hidden)]
 pub use timing_traits::*;
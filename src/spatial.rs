@@ -0,0 +1,500 @@
+//! Calculates where playfield objects are in the world at a given time.
+//!
+//! Positions use the game's coordinate system, in meters:
+//! - `+X` is to the player's right, with the center of the grid at zero.
+//! - `+Y` is up, with the floor at zero.
+//! - `+Z` is forward, with objects reaching `Z = 0` on their beat.
+//!
+//! Objects spawn half a [jump distance](crate::info::DifficultyInfo::get_jump_distance) in front of the player,
+//! move towards the player at the NJS, and despawn half a jump distance behind the player.
+//! While moving in, notes and bombs jump from the bottom row up to their own row, reaching it on their beat.
+//!
+//! [`LaneRotationEvent`](crate::LaneRotationEvent)s rotate objects around the player (at the origin), as resolved by a [`LaneRotationTimeline`].
+//! Modded features, such as player height and noodle extensions, are not supported.
+
+use crate::difficulty::Difficulty;
+use crate::difficulty::bpm::BpmTimeline;
+use crate::difficulty::lane_rotation::LaneRotationTimeline;
+use crate::difficulty::playfield::{Arc, Bomb, Chain, CutDirection, Note, Wall};
+use crate::info::DifficultyInfo;
+use std::ops::{Add, Mul, Sub};
+
+mod arc;
+mod chain;
+
+/// The distance (in meters) between the centers of two columns.
+pub const LANE_WIDTH: f32 = 0.6;
+/// The distance (in meters) between the centers of two rows.
+pub const LAYER_HEIGHT: f32 = 0.6;
+/// The height (in meters) of the center of the bottom row.
+pub const BASE_LAYER_Y: f32 = 0.25;
+
+/// A position or size in the world, in meters.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Rotates the vector clockwise around the Y axis (when looking down), by a number of degrees.
+    pub fn rotate_y(self, degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::new(
+            self.x * cos + self.z * sin,
+            self.y,
+            self.z * cos - self.x * sin,
+        )
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+/// A point along the path of an [`Arc`] or [`Chain`], on the grid plane.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct CurvePoint {
+    /// The beat that the point reaches the player.
+    pub beat: f32,
+    /// The horizontal position (in meters), with the center of the grid at zero.
+    pub x: f32,
+    /// The vertical position (in meters), with the floor at zero.
+    pub y: f32,
+}
+
+/// A single link of a [`Chain`], as placed by [`Chain::links`].
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct ChainLink {
+    pub point: CurvePoint,
+    /// The rotation (in degrees) of the link, following the path of the chain, with zero being downwards.
+    pub roll: f32,
+}
+
+/// The position and rotation of an object.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct Transform {
+    /// The center of the object.
+    pub position: Vec3,
+    /// The rotation (in degrees) around the player, clockwise when looking down.
+    ///
+    /// This has already been applied to the [position](Self::position).
+    pub yaw: f32,
+    /// The rotation (in degrees) of the object around the Z axis, with zero being a downward note.
+    pub roll: f32,
+}
+
+/// The transform and size of a [`Wall`].
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct WallTransform {
+    pub transform: Transform,
+    /// The width, height and length of the wall, before rotation.
+    pub size: Vec3,
+}
+
+/// The transforms of both ends of an [`Arc`] or [`Chain`].
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct SpanTransform {
+    pub head: Transform,
+    pub tail: Transform,
+}
+
+/// Calculates the world-space transforms of a difficulty's objects over time.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(Debug, Clone, PartialEq)
+)]
+pub struct Spatial {
+    timeline: BpmTimeline,
+    lane_rotation: LaneRotationTimeline,
+    njs: f32,
+    reaction_time: f32,
+}
+
+impl Spatial {
+    /// Creates a new context from a difficulty and its settings.
+    ///
+    /// The base BPM is defined by the map's [info file](crate::info::Beatmap::bpm).
+    pub fn new(difficulty: &Difficulty, info: &DifficultyInfo, base_bpm: f32) -> Self {
+        Self {
+            timeline: difficulty.get_bpm_timeline(base_bpm),
+            lane_rotation: difficulty.get_lane_rotation_timeline(),
+            njs: info.get_njs(),
+            reaction_time: info.get_reaction_time(base_bpm),
+        }
+    }
+
+    /// Returns the BPM timeline used to convert beats into seconds.
+    pub fn get_bpm_timeline(&self) -> &BpmTimeline {
+        &self.timeline
+    }
+
+    /// Returns the lane rotation timeline used to rotate objects around the player.
+    pub fn get_lane_rotation_timeline(&self) -> &LaneRotationTimeline {
+        &self.lane_rotation
+    }
+
+    /// Returns the total rotation (in degrees) of objects at a beat, caused by [`LaneRotationEvent`](crate::LaneRotationEvent)s.
+    pub fn get_lane_rotation(&self, beat: f32) -> f32 {
+        self.lane_rotation.get_rotation(beat)
+    }
+
+    /// Returns the transform of a note at a time (in seconds), or `None` if the note is not spawned.
+    pub fn get_note_transform(&self, note: &Note, seconds: f32) -> Option<Transform> {
+        let roll = match note.direction {
+            CutDirection::Any | CutDirection::Undefined(_) => 0.0,
+            direction => direction.get_degrees(),
+        } + note.angle_offset;
+
+        self.is_spawned(note.beat, note.beat, seconds)
+            .then(|| self.get_grid_transform(note.beat, note.row, note.col, roll, seconds))
+    }
+
+    /// Returns the transform of a bomb at a time (in seconds), or `None` if the bomb is not spawned.
+    pub fn get_bomb_transform(&self, bomb: &Bomb, seconds: f32) -> Option<Transform> {
+        self.is_spawned(bomb.beat, bomb.beat, seconds)
+            .then(|| self.get_grid_transform(bomb.beat, bomb.row, bomb.col, 0.0, seconds))
+    }
+
+    /// Returns the transform and size of a wall at a time (in seconds), or `None` if the wall is not spawned.
+    ///
+    /// Walls do not jump, and the position is the center of the wall.
+    pub fn get_wall_transform(&self, wall: &Wall, seconds: f32) -> Option<WallTransform> {
+        let end_beat = wall.beat + wall.duration;
+        if !self.is_spawned(wall.beat, end_beat, seconds) {
+            return None;
+        }
+
+        let start_z = self.get_z(wall.beat, seconds);
+        let end_z = self.get_z(end_beat, seconds);
+        let size = Vec3::new(
+            wall.width as f32 * LANE_WIDTH,
+            wall.height as f32 * LAYER_HEIGHT,
+            end_z - start_z,
+        );
+
+        let yaw = self.get_lane_rotation(wall.beat);
+        let center = Vec3::new(
+            get_lane_x(wall.col as f32) + (size.x - LANE_WIDTH) / 2.0,
+            wall.row as f32 * LAYER_HEIGHT + size.y / 2.0,
+            (start_z + end_z) / 2.0,
+        );
+
+        Some(WallTransform {
+            transform: Transform {
+                position: center.rotate_y(yaw),
+                yaw,
+                roll: 0.0,
+            },
+            size,
+        })
+    }
+
+    /// Returns the transforms of the head and tail of an arc at a time (in seconds),
+    /// or `None` if no part of the arc is spawned.
+    pub fn get_arc_transform(&self, arc: &Arc, seconds: f32) -> Option<SpanTransform> {
+        self.is_spawned(arc.beat, arc.tail_beat, seconds)
+            .then(|| SpanTransform {
+                head: self.get_grid_transform(
+                    arc.beat,
+                    arc.row,
+                    arc.col,
+                    arc.direction.get_degrees(),
+                    seconds,
+                ),
+                tail: self.get_grid_transform(
+                    arc.tail_beat,
+                    arc.tail_row,
+                    arc.tail_col,
+                    arc.tail_direction.get_degrees(),
+                    seconds,
+                ),
+            })
+    }
+
+    /// Returns the transforms of the head and tail of a chain at a time (in seconds),
+    /// or `None` if no part of the chain is spawned.
+    pub fn get_chain_transform(&self, chain: &Chain, seconds: f32) -> Option<SpanTransform> {
+        let roll = chain.direction.get_degrees();
+
+        self.is_spawned(chain.beat, chain.tail_beat, seconds)
+            .then(|| SpanTransform {
+                head: self.get_grid_transform(chain.beat, chain.row, chain.col, roll, seconds),
+                tail: self.get_grid_transform(
+                    chain.tail_beat,
+                    chain.tail_row,
+                    chain.tail_col,
+                    roll,
+                    seconds,
+                ),
+            })
+    }
+
+    /// Returns the transforms of each link of a chain that is spawned at a time (in seconds), excluding the head.
+    ///
+    /// See [`Chain::links`] for how the links are placed.
+    pub fn get_chain_link_transforms(&self, chain: &Chain, seconds: f32) -> Vec<Transform> {
+        chain
+            .links()
+            .iter()
+            .filter(|link| self.is_spawned(link.point.beat, link.point.beat, seconds))
+            .map(|link| {
+                self.get_jumping_transform(
+                    link.point.beat,
+                    link.point.x,
+                    link.point.y,
+                    link.roll,
+                    seconds,
+                )
+            })
+            .collect()
+    }
+
+    /// Returns true if an object is between spawning and despawning.
+    fn is_spawned(&self, beat: f32, end_beat: f32, seconds: f32) -> bool {
+        let start = self.timeline.get_seconds(beat);
+        let end = self.timeline.get_seconds(end_beat);
+        seconds >= start - self.reaction_time && seconds <= end + self.reaction_time
+    }
+
+    /// Returns the distance in front of the player of something that reaches the player at a beat.
+    fn get_z(&self, beat: f32, seconds: f32) -> f32 {
+        (self.timeline.get_seconds(beat) - seconds) * self.njs
+    }
+
+    /// Returns the transform of an object on the grid that jumps from the bottom row to its own row.
+    fn get_grid_transform(
+        &self,
+        beat: f32,
+        row: i32,
+        col: i32,
+        roll: f32,
+        seconds: f32,
+    ) -> Transform {
+        let x = get_lane_x(col as f32);
+        let y = get_layer_y(row as f32);
+        self.get_jumping_transform(beat, x, y, roll, seconds)
+    }
+
+    /// Returns the transform of an object that jumps from the bottom row to its own height.
+    fn get_jumping_transform(
+        &self,
+        beat: f32,
+        x: f32,
+        end_y: f32,
+        roll: f32,
+        seconds: f32,
+    ) -> Transform {
+        let remaining = self.timeline.get_seconds(beat) - seconds;
+
+        let y = if remaining > 0.0 && self.reaction_time > 0.0 {
+            // Decelerates upwards, like it was thrown, reaching the row with no vertical velocity.
+            let left = (remaining / self.reaction_time).min(1.0);
+            end_y + (BASE_LAYER_Y - end_y) * left * left
+        } else {
+            end_y
+        };
+
+        let yaw = self.get_lane_rotation(beat);
+        let position = Vec3::new(x, y, remaining * self.njs);
+
+        Transform {
+            position: position.rotate_y(yaw),
+            yaw,
+            roll,
+        }
+    }
+}
+
+/// Returns the X position of the center of a column.
+///
+/// Columns can be fractional, such as when sampling a curve between columns.
+pub fn get_lane_x(col: f32) -> f32 {
+    (col - 1.5) * LANE_WIDTH
+}
+
+/// Returns the Y position of the center of a row.
+///
+/// Rows can be fractional, such as when sampling a curve between rows.
+pub fn get_layer_y(row: f32) -> f32 {
+    BASE_LAYER_Y + row * LAYER_HEIGHT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::gameplay_event::{ExecutionTime, LaneRotationEvent};
+
+    fn get_spatial(difficulty: &Difficulty) -> Spatial {
+        // A reaction time of one second, and a jump distance of 20 meters.
+        let info = DifficultyInfo {
+            njs: 10.0,
+            ..Default::default()
+        };
+        Spatial::new(difficulty, &info, 240.0)
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).x.abs() < 0.0001, "{a:?} != {b:?}");
+        assert!((a - b).y.abs() < 0.0001, "{a:?} != {b:?}");
+        assert!((a - b).z.abs() < 0.0001, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn note_jumps_in() {
+        let spatial = get_spatial(&Difficulty::default());
+        let note = Note {
+            beat: 8.0,
+            row: 2,
+            col: 3,
+            direction: CutDirection::Left,
+            angle_offset: 15.0,
+            ..Default::default()
+        };
+
+        assert_eq!(spatial.get_note_transform(&note, 0.9), None);
+
+        let spawn = spatial.get_note_transform(&note, 1.0).unwrap();
+        assert_close(spawn.position, Vec3::new(0.9, BASE_LAYER_Y, 10.0));
+
+        let halfway = spatial.get_note_transform(&note, 1.5).unwrap();
+        assert_close(halfway.position, Vec3::new(0.9, 1.45 - 1.2 * 0.25, 5.0));
+
+        let hit = spatial.get_note_transform(&note, 2.0).unwrap();
+        assert_close(hit.position, Vec3::new(0.9, 1.45, 0.0));
+        assert_eq!(hit.roll, -75.0);
+
+        let passed = spatial.get_note_transform(&note, 3.0).unwrap();
+        assert_close(passed.position, Vec3::new(0.9, 1.45, -10.0));
+        assert_eq!(spatial.get_note_transform(&note, 3.1), None);
+    }
+
+    #[test]
+    fn lane_rotation() {
+        let difficulty = Difficulty {
+            lane_rotation_events: vec![
+                LaneRotationEvent {
+                    beat: 4.0,
+                    execution_time: ExecutionTime::Early,
+                    degrees: 45.0,
+                },
+                LaneRotationEvent {
+                    beat: 8.0,
+                    execution_time: ExecutionTime::Late,
+                    degrees: 45.0,
+                },
+            ],
+            ..Default::default()
+        };
+        let spatial = get_spatial(&difficulty);
+
+        assert_eq!(spatial.get_lane_rotation(3.0), 0.0);
+        assert_eq!(spatial.get_lane_rotation(4.0), 45.0);
+        assert_eq!(spatial.get_lane_rotation(8.0), 45.0);
+        assert_eq!(spatial.get_lane_rotation(8.5), 90.0);
+
+        let bomb = Bomb {
+            beat: 9.0,
+            row: 0,
+            col: 1,
+        };
+        let transform = spatial.get_bomb_transform(&bomb, 1.25).unwrap();
+        assert_eq!(transform.yaw, 90.0);
+        assert_close(transform.position, Vec3::new(10.0, BASE_LAYER_Y, 0.3));
+    }
+
+    #[test]
+    fn wall_size() {
+        let spatial = get_spatial(&Difficulty::default());
+        let wall = Wall {
+            beat: 4.0,
+            duration: 2.0,
+            row: 2,
+            col: 0,
+            width: 2,
+            height: 3,
+        };
+
+        let transform = spatial.get_wall_transform(&wall, 1.0).unwrap();
+        assert_close(transform.size, Vec3::new(1.2, 1.8, 5.0));
+        assert_close(transform.transform.position, Vec3::new(-0.6, 2.1, 2.5));
+
+        assert!(spatial.get_wall_transform(&wall, 2.5).is_some());
+        assert_eq!(spatial.get_wall_transform(&wall, 2.6), None);
+    }
+
+    #[test]
+    fn arc_spans_head_and_tail() {
+        let spatial = get_spatial(&Difficulty::default());
+        let arc = Arc {
+            beat: 4.0,
+            tail_beat: 8.0,
+            ..Default::default()
+        };
+
+        let transform = spatial.get_arc_transform(&arc, 1.0).unwrap();
+        assert_eq!(transform.head.position.z, 0.0);
+        assert_eq!(transform.tail.position.z, 10.0);
+        assert!(spatial.get_arc_transform(&arc, 2.9).is_some());
+        assert_eq!(spatial.get_arc_transform(&arc, 3.1), None);
+    }
+}
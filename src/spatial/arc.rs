@@ -0,0 +1,194 @@
+//! Evaluates the curve of an [`Arc`].
+//!
+//! An arc is a cubic Bézier curve from its head to its tail, with control points placed in the
+//! [head direction](Arc::direction) and against the [tail direction](Arc::tail_direction).
+//!
+//! If the head and tail have the same direction, a clockwise or counter-clockwise [`MidAnchorMode`]
+//! loops the arc around a mid anchor, placed to the side of the line between the head and tail.
+//! The arc is then split into two curves, passing through the mid anchor against the head direction.
+
+use crate::difficulty::playfield::{Arc, CutDirection, MidAnchorMode};
+use crate::spatial::{CurvePoint, Vec3, get_lane_x, get_layer_y};
+
+/// The distance (in meters) of a control point from the head or tail, when its multiplier is one.
+const CONTROL_POINT_DISTANCE: f32 = 1.5;
+
+impl Arc {
+    /// Returns a point along the arc, where `t` is in the range 0..1 inclusive (from head to tail).
+    ///
+    /// Values outside of this range are clamped.
+    pub fn sample(&self, t: f32) -> CurvePoint {
+        let t = t.clamp(0.0, 1.0);
+        let head = get_grid_position(self.col, self.row);
+        let tail = get_grid_position(self.tail_col, self.tail_row);
+        let head_direction = get_direction(self.direction);
+        let tail_direction = get_direction(self.tail_direction);
+
+        let head_control = head + head_direction * (self.control_point * CONTROL_POINT_DISTANCE);
+        let tail_control =
+            tail - tail_direction * (self.tail_control_point * CONTROL_POINT_DISTANCE);
+
+        let position = match self.get_mid_anchor(head, tail) {
+            None => cubic_bezier(head, head_control, tail_control, tail, t),
+            Some(mid) => {
+                let mid_direction = head_direction * -(CONTROL_POINT_DISTANCE / 2.0);
+                if t < 0.5 {
+                    cubic_bezier(head, head_control, mid - mid_direction, mid, t * 2.0)
+                } else {
+                    cubic_bezier(mid, mid + mid_direction, tail_control, tail, t * 2.0 - 1.0)
+                }
+            }
+        };
+
+        CurvePoint {
+            beat: self.beat + (self.tail_beat - self.beat) * t,
+            x: position.x,
+            y: position.y,
+        }
+    }
+
+    /// Returns evenly spaced (in time) points along the arc, split into a number of segments.
+    ///
+    /// This includes both the head and tail, so there will be `segments + 1` points.
+    pub fn to_polyline(&self, segments: usize) -> Vec<CurvePoint> {
+        let segments = segments.max(1);
+        (0..=segments)
+            .map(|i| self.sample(i as f32 / segments as f32))
+            .collect()
+    }
+
+    /// Returns the position of the mid anchor, or `None` if the arc does not have one.
+    fn get_mid_anchor(&self, head: Vec3, tail: Vec3) -> Option<Vec3> {
+        if self.direction != self.tail_direction
+            || matches!(
+                self.direction,
+                CutDirection::Any | CutDirection::Undefined(_)
+            )
+        {
+            return None;
+        }
+
+        // Rotating the head direction by -90 degrees gives its clockwise side.
+        let side_degrees = match self.mid_anchor_mode {
+            MidAnchorMode::Clockwise => -90.0,
+            MidAnchorMode::CounterClockwise => 90.0,
+            _ => return None,
+        };
+        let side = get_degrees_direction(self.direction.get_degrees() + side_degrees);
+
+        Some((head + tail) * 0.5 + side * (CONTROL_POINT_DISTANCE / 2.0))
+    }
+}
+
+/// Returns the position of a grid cell on the grid plane, in meters.
+fn get_grid_position(col: i32, row: i32) -> Vec3 {
+    Vec3::new(get_lane_x(col as f32), get_layer_y(row as f32), 0.0)
+}
+
+/// Returns the unit vector of a cut direction, or zero for dots.
+fn get_direction(direction: CutDirection) -> Vec3 {
+    match direction {
+        CutDirection::Any | CutDirection::Undefined(_) => Vec3::ZERO,
+        direction => get_degrees_direction(direction.get_degrees()),
+    }
+}
+
+/// Returns the unit vector of an angle, with zero degrees pointing down.
+fn get_degrees_direction(degrees: f32) -> Vec3 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    Vec3::new(sin, -cos, 0.0)
+}
+
+fn cubic_bezier(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let u = 1.0 - t;
+    p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(point: CurvePoint, x: f32, y: f32) {
+        assert!(
+            (point.x - x).abs() < 0.0001 && (point.y - y).abs() < 0.0001,
+            "{point:?} != ({x}, {y})"
+        );
+    }
+
+    #[test]
+    fn straight_arc() {
+        let arc = Arc {
+            beat: 2.0,
+            row: 2,
+            col: 1,
+            direction: CutDirection::Down,
+            tail_beat: 4.0,
+            tail_row: 0,
+            tail_col: 1,
+            tail_direction: CutDirection::Down,
+            ..Default::default()
+        };
+
+        assert_close(arc.sample(0.0), -0.3, 1.45);
+        assert_close(arc.sample(0.5), -0.3, 0.85);
+        assert_close(arc.sample(1.0), -0.3, 0.25);
+        assert_eq!(arc.sample(0.5).beat, 3.0);
+        assert_eq!(arc.sample(2.0), arc.sample(1.0));
+    }
+
+    #[test]
+    fn u_turn_bulges_towards_head_direction() {
+        let arc = Arc {
+            row: 1,
+            col: 1,
+            direction: CutDirection::Down,
+            tail_row: 1,
+            tail_col: 2,
+            tail_direction: CutDirection::Up,
+            ..Default::default()
+        };
+
+        let mid = arc.sample(0.5);
+        assert_close(mid, 0.0, 0.85 - 0.75 * 1.5);
+    }
+
+    #[test]
+    fn mid_anchor_sides() {
+        let arc = Arc {
+            row: 1,
+            col: 1,
+            direction: CutDirection::Down,
+            tail_row: 1,
+            tail_col: 1,
+            tail_direction: CutDirection::Down,
+            mid_anchor_mode: MidAnchorMode::Clockwise,
+            ..Default::default()
+        };
+
+        // Clockwise from a downward swing curves to the left.
+        assert_close(arc.sample(0.5), -0.3 - 0.75, 0.85);
+
+        let arc = Arc {
+            mid_anchor_mode: MidAnchorMode::CounterClockwise,
+            ..arc
+        };
+        assert_close(arc.sample(0.5), -0.3 + 0.75, 0.85);
+
+        let arc = Arc {
+            mid_anchor_mode: MidAnchorMode::Straight,
+            ..arc
+        };
+        assert_close(arc.sample(0.5), -0.3, 0.85);
+    }
+
+    #[test]
+    fn polyline() {
+        let arc = Arc::default();
+        let points = arc.to_polyline(4);
+
+        assert_eq!(points.len(), 5);
+        assert_eq!(points[0], arc.sample(0.0));
+        assert_eq!(points[4], arc.sample(1.0));
+        assert_eq!(points[1].beat, 0.25);
+    }
+}
@@ -0,0 +1,158 @@
+//! Places the links of a [`Chain`].
+//!
+//! A chain follows a quadratic Bézier curve from its head to its tail, with the control point placed
+//! in the [head direction](Chain::direction), halfway between the head and tail.
+//! Links are evenly spaced in time, while their positions are [squished](Chain::link_squish) towards the head.
+
+use crate::difficulty::playfield::{Chain, CutDirection};
+use crate::spatial::{ChainLink, CurvePoint, Vec3, get_lane_x, get_layer_y};
+
+impl Chain {
+    /// Returns the position, beat and rotation of each link, excluding the head (which is a [`Note`](crate::Note)).
+    ///
+    /// There will be one less link than the [link count](Chain::link_count),
+    /// with the last link at the tail (unless squished).
+    pub fn links(&self) -> Vec<ChainLink> {
+        if self.link_count <= 1 {
+            return Vec::new();
+        }
+
+        let head = Vec3::new(
+            get_lane_x(self.col as f32),
+            get_layer_y(self.row as f32),
+            0.0,
+        );
+        let tail = Vec3::new(
+            get_lane_x(self.tail_col as f32),
+            get_layer_y(self.tail_row as f32),
+            0.0,
+        );
+
+        let direction = match self.direction {
+            CutDirection::Any | CutDirection::Undefined(_) => Vec3::ZERO,
+            direction => {
+                let (sin, cos) = direction.get_degrees().to_radians().sin_cos();
+                Vec3::new(sin, -cos, 0.0)
+            }
+        };
+        let distance = (tail.x - head.x).hypot(tail.y - head.y);
+        let control = head + direction * (distance / 2.0);
+
+        let segments = (self.link_count - 1) as f32;
+        (1..self.link_count)
+            .map(|i| {
+                let t = i as f32 / segments;
+                let curve_t = t * self.link_squish;
+                let position = quadratic_bezier(head, control, tail, curve_t);
+
+                let tangent =
+                    (control - head) * (2.0 * (1.0 - curve_t)) + (tail - control) * (2.0 * curve_t);
+                let roll = if tangent.x == 0.0 && tangent.y == 0.0 {
+                    self.direction.get_degrees()
+                } else {
+                    tangent.x.atan2(-tangent.y).to_degrees()
+                };
+
+                ChainLink {
+                    point: CurvePoint {
+                        beat: self.beat + (self.tail_beat - self.beat) * t,
+                        x: position.x,
+                        y: position.y,
+                    },
+                    roll,
+                }
+            })
+            .collect()
+    }
+}
+
+fn quadratic_bezier(p0: Vec3, p1: Vec3, p2: Vec3, t: f32) -> Vec3 {
+    let u = 1.0 - t;
+    p0 * (u * u) + p1 * (2.0 * u * t) + p2 * (t * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.0001, "{a} != {b}");
+    }
+
+    #[test]
+    fn straight_chain() {
+        let chain = Chain {
+            beat: 1.0,
+            row: 2,
+            col: 1,
+            direction: CutDirection::Down,
+            tail_beat: 1.5,
+            tail_row: 0,
+            tail_col: 1,
+            link_count: 5,
+            link_squish: 1.0,
+            ..Default::default()
+        };
+
+        let links = chain.links();
+        assert_eq!(links.len(), 4);
+
+        let beats: Vec<f32> = links.iter().map(|link| link.point.beat).collect();
+        assert_eq!(beats, vec![1.125, 1.25, 1.375, 1.5]);
+
+        assert_close(links[3].point.x, -0.3);
+        assert_close(links[3].point.y, 0.25);
+        assert!(links.iter().all(|link| link.roll.abs() < 0.0001));
+    }
+
+    #[test]
+    fn squish_keeps_timing() {
+        let chain = Chain {
+            row: 2,
+            col: 1,
+            direction: CutDirection::Down,
+            tail_beat: 1.0,
+            tail_row: 0,
+            tail_col: 1,
+            link_count: 3,
+            link_squish: 0.5,
+            ..Default::default()
+        };
+
+        let links = chain.links();
+        assert_eq!(links[1].point.beat, 1.0);
+        // Halfway between the top and bottom rows.
+        assert_close(links[1].point.y, 0.85);
+    }
+
+    #[test]
+    fn curved_chain_rotates() {
+        let chain = Chain {
+            row: 0,
+            col: 0,
+            direction: CutDirection::Down,
+            tail_beat: 1.0,
+            tail_row: 0,
+            tail_col: 2,
+            link_count: 2,
+            link_squish: 1.0,
+            ..Default::default()
+        };
+
+        let links = chain.links();
+        assert_eq!(links.len(), 1);
+        assert_close(links[0].point.x, 0.3);
+        // Moving right and upwards at the tail.
+        assert!(links[0].roll > 90.0 && links[0].roll < 180.0);
+    }
+
+    #[test]
+    fn no_links() {
+        let chain = Chain {
+            link_count: 1,
+            ..Default::default()
+        };
+
+        assert!(chain.links().is_empty());
+    }
+}
@@ -1,9 +1,28 @@
-//! Traits that are used to get an object's position in time and duration.
+//! Traits that are used to get and change an object's position in time and duration.
+//!
+//! Beats are stored as `f32`. The `f64` methods are only convenience conversions for code that works in `f64`,
+//! and don't add any precision.
 
 /// Represents any beatmap object that happens at a specific beat.
 pub trait Timed {
     /// Returns the beat that an object takes place.
     fn get_beat(&self) -> f32;
+
+    /// Returns the beat that an object takes place, converted to an `f64`.
+    fn get_beat_f64(&self) -> f64 {
+        self.get_beat() as f64
+    }
+}
+
+/// Represents any beatmap object that happens at a specific beat, which can be changed.
+pub trait TimedMut: Timed {
+    /// Moves an object to a new beat. Objects with a [`Duration`] keep their duration.
+    fn set_beat(&mut self, beat: f32);
+
+    /// Moves an object to a new beat, converted from an `f64`.
+    fn set_beat_f64(&mut self, beat: f64) {
+        self.set_beat(beat as f32);
+    }
 }
 
 /// Represents any beatmap object that happens over a duration of time in beats.
@@ -12,6 +31,34 @@ pub trait Duration: Timed {
     fn get_end_beat(&self) -> f32;
     /// Returns the length (in beats) that an object takes place.
     fn get_duration(&self) -> f32;
+
+    /// Returns the beat that an object ends, converted to an `f64`.
+    fn get_end_beat_f64(&self) -> f64 {
+        self.get_end_beat() as f64
+    }
+
+    /// Returns the length (in beats) that an object takes place, converted to an `f64`.
+    fn get_duration_f64(&self) -> f64 {
+        self.get_duration() as f64
+    }
+}
+
+/// Represents any beatmap object that happens over a duration of time in beats, which can be changed.
+pub trait DurationMut: Duration + TimedMut {
+    /// Changes the beat that an object ends, without changing its start beat.
+    fn set_end_beat(&mut self, end_beat: f32);
+    /// Changes the length (in beats) of an object, without changing its start beat.
+    fn set_duration(&mut self, duration: f32);
+
+    /// Changes the beat that an object ends, converted from an `f64`.
+    fn set_end_beat_f64(&mut self, end_beat: f64) {
+        self.set_end_beat(end_beat as f32);
+    }
+
+    /// Changes the length (in beats) of an object, converted from an `f64`.
+    fn set_duration_f64(&mut self, duration: f64) {
+        self.set_duration(duration as f32);
+    }
 }
 
 #[macro_export]
@@ -23,6 +70,12 @@ macro_rules! impl_timed {
                 self.$beat
             }
         }
+
+        impl crate::timing_traits::TimedMut for $ident {
+            fn set_beat(&mut self, beat: f32) {
+                self.$beat = beat;
+            }
+        }
     };
 }
 
@@ -30,7 +83,18 @@ macro_rules! impl_timed {
 #[doc(hidden)]
 macro_rules! impl_duration {
     ($ident:ident::$beat:ident, end: $end:ident) => {
-        impl_timed!($ident::$beat);
+        impl crate::timing_traits::Timed for $ident {
+            fn get_beat(&self) -> f32 {
+                self.$beat
+            }
+        }
+
+        impl crate::timing_traits::TimedMut for $ident {
+            fn set_beat(&mut self, beat: f32) {
+                self.$end += beat - self.$beat;
+                self.$beat = beat;
+            }
+        }
 
         impl crate::timing_traits::Duration for $ident {
             fn get_end_beat(&self) -> f32 {
@@ -41,6 +105,16 @@ macro_rules! impl_duration {
                 self.$end - self.$beat
             }
         }
+
+        impl crate::timing_traits::DurationMut for $ident {
+            fn set_end_beat(&mut self, end_beat: f32) {
+                self.$end = end_beat;
+            }
+
+            fn set_duration(&mut self, duration: f32) {
+                self.$end = self.$beat + duration;
+            }
+        }
     };
     ($ident:ident::$beat:ident, duration: $duration:ident) => {
         impl_timed!($ident::$beat);
@@ -54,5 +128,15 @@ macro_rules! impl_duration {
                 self.$duration
             }
         }
+
+        impl crate::timing_traits::DurationMut for $ident {
+            fn set_end_beat(&mut self, end_beat: f32) {
+                self.$duration = end_beat - self.$beat;
+            }
+
+            fn set_duration(&mut self, duration: f32) {
+                self.$duration = duration;
+            }
+        }
     };
 }